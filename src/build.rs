@@ -1,110 +1,412 @@
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
+use crate::config::{BuildOptions, Module};
+use crate::maven::{read_pom, Pom};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use chrono::Local;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::Path;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use walkdir::WalkDir;
 use zip::{write::FileOptions, ZipWriter};
-use std::fs::File;
-
-/// 打包 Java 项目
-pub fn build_java_project(project_dir: &str) -> Result<(), String> {
-    // 检测操作系统类型
-    let is_windows = cfg!(target_os = "windows");
-    
-    // 根据操作系统类型选择适当的命令
-    let mut child = if is_windows {
-        Command::new("cmd")
-            .args(["/c", "mvn", "clean", "package", "-DskipTests"])
-            .current_dir(project_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-    } else {
-        Command::new("mvn")
-            .args(["clean", "package", "-DskipTests"])
-            .current_dir(project_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
+
+/// 构建失败时在错误信息中显示的最后几行输出
+const FAILURE_TAIL_LINES: usize = 30;
+
+/// Java项目的构建工具
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildTool {
+    Maven,
+    Gradle,
+    /// 按项目中的构建文件自动识别
+    #[default]
+    Auto,
+}
+
+impl BuildTool {
+    /// 确定实际使用的构建工具: 有pom.xml时使用Maven,否则有build.gradle(.kts)或gradlew时使用Gradle
+    pub fn resolve(self, project_dir: &str) -> BuildTool {
+        if self != BuildTool::Auto {
+            return self;
+        }
+        let dir = Path::new(project_dir);
+        if dir.join("pom.xml").is_file() {
+            return BuildTool::Maven;
+        }
+        let gradle = ["build.gradle", "build.gradle.kts", "gradlew"]
+            .iter()
+            .any(|file| dir.join(file).is_file());
+        if gradle {
+            BuildTool::Gradle
+        } else {
+            BuildTool::Maven
+        }
     }
-    .map_err(|e| format!("执行mvn命令失败: {}", e))?;
 
-    // 读取并显示标准输出
-    if let Some(stdout) = child.stdout.take() {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                println!("{}", line);
+    fn label(self) -> &'static str {
+        match self {
+            BuildTool::Gradle => "Gradle",
+            _ => "Maven",
+        }
+    }
+
+    fn wrapper_hint(self) -> &'static str {
+        match self {
+            BuildTool::Gradle => "gradlew",
+            _ => "mvnw",
+        }
+    }
+
+    /// 未配置artifact时依次尝试的编译产物路径
+    ///
+    /// Maven: jar_files中的jar包名 (或{name}.jar) > pom.xml的build.finalName > target/*.jar;
+    /// Gradle: jar_files中的jar包名 > {name}*.jar (默认带版本号) > build/libs/*.jar
+    fn default_artifacts(self, module: &Module, module_dir: &Path) -> Vec<String> {
+        let jar_name = module
+            .jar_name
+            .clone()
+            .unwrap_or_else(|| format!("{}.jar", module.name));
+        match self {
+            BuildTool::Gradle => vec![
+                format!("build/libs/{}", jar_name),
+                format!("build/libs/{}*.jar", module.name),
+                "build/libs/*.jar".to_string(),
+            ],
+            _ => {
+                let mut artifacts = vec![format!("target/{}", jar_name)];
+                match read_pom(module_dir) {
+                    Ok(Some(Pom {
+                        final_name: Some(final_name),
+                        ..
+                    })) => artifacts.push(format!("target/{}.jar", final_name)),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("{}", e),
+                }
+                artifacts.push("target/*.jar".to_string());
+                artifacts
             }
         }
     }
 
-    // 等待命令执行完成
-    let status = child.wait()
-        .map_err(|e| format!("等待命令完成失败: {}", e))?;
+    /// 构建命令和参数,默认优先使用项目中的mvnw/gradlew
+    ///
+    /// `source_dirs` 不为空时只构建这些目录下的模块及其依赖: Maven使用 `-pl <dir> -am`,
+    /// Gradle把任务限定到对应的子项目 (例如 `:admin:bootJar`)。
+    fn command(self, project_dir: &str, options: &BuildOptions, source_dirs: &[String]) -> (String, Vec<String>) {
+        let (wrapper, global) = match (self, cfg!(target_os = "windows")) {
+            (BuildTool::Gradle, true) => ("gradlew.bat", "gradle"),
+            (BuildTool::Gradle, false) => ("gradlew", "gradle"),
+            (_, true) => ("mvnw.cmd", "mvn"),
+            (_, false) => ("mvnw", "mvn"),
+        };
+        let program = options.build_command.clone().unwrap_or_else(|| {
+            if Path::new(project_dir).join(wrapper).is_file() {
+                format!(".{}{}", std::path::MAIN_SEPARATOR, wrapper)
+            } else {
+                global.to_string()
+            }
+        });
 
-    if status.success() {
-        println!("Java 项目构建成功!");
-        Ok(())
-    } else {
-        // 读取错误输出
-        if let Some(stderr) = child.stderr.take() {
-            let reader = BufReader::new(stderr);
-            let error = reader.lines()
-                .filter_map(|line| line.ok())
-                .collect::<Vec<String>>()
-                .join("\n");            
-            Err(format!("构建失败:请检查mvn是否配置在环境变量中\n{}", error))
+        // 单模块项目的源码目录为项目根目录,无法只构建部分模块
+        let selected: Vec<&str> = if source_dirs.iter().any(|dir| dir == ".") {
+            vec![]
         } else {
-            Err("构建失败，无法获取错误信息".to_string())
+            source_dirs.iter().map(|dir| dir.trim_end_matches('/')).collect()
+        };
+
+        let mut args: Vec<String> = match self {
+            BuildTool::Gradle => {
+                let tasks: &[&str] = if options.skip_tests {
+                    &["clean", "bootJar"]
+                } else {
+                    &["clean", "test", "bootJar"]
+                };
+                if selected.is_empty() {
+                    tasks.iter().map(|task| task.to_string()).collect()
+                } else {
+                    // 子项目路径: services/admin -> :services:admin
+                    selected
+                        .iter()
+                        .flat_map(|dir| {
+                            let project = format!(":{}", dir.replace(['/', '\\'], ":"));
+                            tasks.iter().map(move |task| format!("{}:{}", project, task))
+                        })
+                        .collect()
+                }
+            }
+            _ => {
+                let mut args = vec!["clean".to_string(), "package".to_string()];
+                if options.skip_tests {
+                    args.push("-DskipTests".to_string());
+                }
+                if !selected.is_empty() {
+                    args.push("-pl".to_string());
+                    args.push(selected.join(","));
+                    args.push("-am".to_string());
+                }
+                args
+            }
+        };
+        args.extend(options.build_args.iter().cloned());
+        (program, args)
+    }
+}
+
+/// web端项目的包管理器
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+    Bun,
+    /// 按项目中的锁文件自动识别
+    #[default]
+    Auto,
+}
+
+impl PackageManager {
+    /// 确定实际使用的包管理器: 依次按pnpm-lock.yaml、yarn.lock、bun.lockb(bun.lock)识别,都没有时使用npm
+    pub fn resolve(self, project_dir: &str) -> PackageManager {
+        if self != PackageManager::Auto {
+            return self;
+        }
+        let dir = Path::new(project_dir);
+        let lockfiles = [
+            ("pnpm-lock.yaml", PackageManager::Pnpm),
+            ("yarn.lock", PackageManager::Yarn),
+            ("bun.lockb", PackageManager::Bun),
+            ("bun.lock", PackageManager::Bun),
+        ];
+        lockfiles
+            .iter()
+            .find(|(file, _)| dir.join(file).is_file())
+            .map(|(_, manager)| *manager)
+            .unwrap_or(PackageManager::Npm)
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Bun => "bun",
+            _ => "npm",
         }
     }
+
+    /// 按锁文件安装依赖的参数,npm项目没有package-lock.json时无法使用npm ci
+    fn install_args(self, project_dir: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            PackageManager::Pnpm | PackageManager::Yarn | PackageManager::Bun => &["install", "--frozen-lockfile"],
+            _ if Path::new(project_dir).join("package-lock.json").is_file() => &["ci"],
+            _ => &["install"],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn hint(self) -> String {
+        format!("请检查{}是否已安装并配置在环境变量中", self.program())
+    }
+}
+
+/// 打包 Java 项目,`source_dirs` 不为空时只构建这些目录下的模块及其依赖
+pub fn build_java_project(
+    project_dir: &str,
+    options: &BuildOptions,
+    source_dirs: &[String],
+) -> Result<(), String> {
+    let build_tool = options.build_tool.resolve(project_dir);
+    let (program, args) = build_tool.command(project_dir, options, source_dirs);
+    println!("使用{}构建: {} {}", build_tool.label(), program, args.join(" "));
+
+    let prefix = match build_tool {
+        BuildTool::Gradle => "gradle",
+        _ => "mvn",
+    };
+    let hint = format!(
+        "请检查{}是否已安装并配置在环境变量中，或在项目中添加{}",
+        program,
+        build_tool.wrapper_hint()
+    );
+    run_build(project_dir, &program, &args, prefix, &hint)?;
+    println!("Java 项目构建成功!");
+    Ok(())
+}
+
+/// 安装web端项目的依赖
+pub fn install_vue_dependencies(project_dir: &str, package_manager: PackageManager) -> Result<(), String> {
+    let program = package_manager.program();
+    let args = package_manager.install_args(project_dir);
+    println!("安装依赖: {} {}", program, args.join(" "));
+    run_build(project_dir, program, &args, &format!("{}:install", program), &package_manager.hint())?;
+    println!("依赖安装成功!");
+    Ok(())
 }
 
 /// 打包 Vue 项目
-pub fn build_vue_project(project_dir: &str,scripts: &str) -> Result<(), String> {
-    // 检测操作系统类型
-    let is_windows = cfg!(target_os = "windows");
-    
-    // 根据操作系统类型选择适当的命令
-    let mut child = if is_windows {
-        Command::new("cmd")
-            .args(["/c", "npm", "run", scripts])
-            .current_dir(project_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
+pub fn build_vue_project(project_dir: &str, package_manager: PackageManager, scripts: &str) -> Result<(), String> {
+    let program = package_manager.program();
+    let args = vec!["run".to_string(), scripts.to_string()];
+    run_build(project_dir, program, &args, &format!("{}:{}", program, scripts), &package_manager.hint())?;
+    println!("{}环境下的Vue项目构建成功!", scripts);
+    Ok(())
+}
+
+/// 执行构建命令,同时读取stdout和stderr并加上[prefix]实时输出,完整输出保存到日志文件
+///
+/// `hint` 是命令无法启动时的提示信息。
+fn run_build(
+    project_dir: &str,
+    program: &str,
+    args: &[String],
+    prefix: &str,
+    hint: &str,
+) -> Result<(), String> {
+    let log_path = build_log_path(prefix);
+    let log = File::create(&log_path)
+        .map(|file| Arc::new(Mutex::new(file)))
+        .map_err(|e| format!("创建构建日志失败 {}: {}", log_path.display(), e))?;
+
+    // Windows下mvn、npm等是.cmd脚本,需要通过cmd执行
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.arg("/c").arg(program);
+        command
     } else {
-        Command::new("npm")
-            .args(["run", scripts])
-            .current_dir(project_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
+        Command::new(program)
+    };
+    let mut child = command
+        .args(args)
+        .current_dir(project_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("执行{}命令失败，{}: {}", program, hint, e))?;
+
+    // 两个管道并发读取,避免其中一个写满缓冲区后子进程阻塞
+    let tail = Arc::new(Mutex::new(VecDeque::with_capacity(FAILURE_TAIL_LINES)));
+    let mut readers = vec![];
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(pump_output(stdout, prefix, false, log.clone(), tail.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(pump_output(stderr, prefix, true, log.clone(), tail.clone()));
     }
-    .map_err(|e| format!("执行npm命令失败: {}", e))?;
 
     let status = child.wait()
         .map_err(|e| format!("等待命令完成失败: {}", e))?;
+    for reader in readers {
+        let _ = reader.join();
+    }
 
     if status.success() {
-        println!("{}环境下的Vue项目构建成功!",scripts);
-        Ok(())
-    } else {
-        // 读取错误输出
-        if let Some(stderr) = child.stderr.take() {
-            let reader = BufReader::new(stderr);
-            let error = reader.lines()
-                .filter_map(|line| line.ok())
-                .collect::<Vec<String>>()
-                .join("\n");
-            Err(format!("构建失败:请检查npm是否配置在环境变量中\n{}", error))
-        } else {
-            Err("构建失败，无法获取错误信息".to_string())
+        return Ok(());
+    }
+    let tail: Vec<String> = tail.lock().map(|tail| tail.iter().cloned().collect()).unwrap_or_default();
+    Err(format!(
+        "构建失败: {} {} ({})\n{}\n完整构建日志: {}",
+        program,
+        args.join(" "),
+        status,
+        tail.join("\n"),
+        log_path.display()
+    ))
+}
+
+/// 逐行读取子进程输出,加上前缀输出到终端并写入日志,保留最后几行用于失败信息
+fn pump_output<R: Read + Send + 'static>(
+    output: R,
+    prefix: &str,
+    stderr: bool,
+    log: Arc<Mutex<File>>,
+    tail: Arc<Mutex<VecDeque<String>>>,
+) -> thread::JoinHandle<()> {
+    let prefix = prefix.to_string();
+    thread::spawn(move || {
+        // 按字节读取,非UTF-8的输出(例如Windows下GBK编码的mvn输出)替换无效字符后继续读取,只在读取出错时停止
+        for line in BufReader::new(output).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line).trim_end_matches('\r').to_string();
+            if stderr {
+                eprintln!("[{}] {}", prefix, line);
+            } else {
+                println!("[{}] {}", prefix, line);
+            }
+            if let Ok(mut log) = log.lock() {
+                let _ = writeln!(log, "{}", line);
+            }
+            if let Ok(mut tail) = tail.lock() {
+                if tail.len() == FAILURE_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        }
+    })
+}
+
+/// 构建日志路径: 系统临时目录/deploy-tool/build-{prefix}-{时间}.log
+fn build_log_path(prefix: &str) -> PathBuf {
+    let dir = env::temp_dir().join("deploy-tool");
+    let _ = fs::create_dir_all(&dir);
+    let name: String = prefix
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    dir.join(format!("build-{}-{}.log", name, Local::now().format("%Y%m%d%H%M%S%3f")))
+}
+
+/// 查找模块的编译产物,artifact支持glob,必须恰好匹配一个文件
+///
+/// 未配置artifact时按构建工具的默认路径依次查找,使用第一个有匹配的路径。
+pub fn find_artifact(project_dir: &str, module: &Module, build_tool: BuildTool) -> Result<String, String> {
+    let module_dir = Path::new(project_dir).join(&module.source_dir);
+    let artifacts = match &module.artifact {
+        Some(artifact) => vec![artifact.clone()],
+        None => build_tool.default_artifacts(module, &module_dir),
+    };
+
+    let mut patterns = vec![];
+    for artifact in artifacts {
+        let pattern = module_dir.join(artifact).to_string_lossy().replace('\\', "/");
+        let matches: Vec<String> = glob::glob(&pattern)
+            .map_err(|e| format!("{}模块的artifact格式错误: {}", module.name, e))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file() && !is_auxiliary_jar(path))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+
+        match matches.len() {
+            0 => patterns.push(pattern),
+            1 => return Ok(matches[0].clone()),
+            _ => {
+                return Err(format!(
+                    "{}模块匹配到多个编译产物,请清理旧的编译产物或通过artifact指定: {}",
+                    module.name,
+                    matches.join(", ")
+                ))
+            }
         }
     }
+    Err(format!(
+        "{}模块没有找到编译产物,请先编译或通过artifact指定,已查找: {}",
+        module.name,
+        patterns.join(", ")
+    ))
 }
 
+/// 源码包、文档包、测试包、Spring Boot重新打包前的原始jar和Gradle不含依赖的-plain.jar不是可部署的编译产物
+fn is_auxiliary_jar(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    ["-sources.jar", "-javadoc.jar", "-tests.jar", "-plain.jar", ".original"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
 
 // 将目录打包成zip文件
 pub fn zip_dir(zip: &mut ZipWriter<File>, src_dir: &str, options: FileOptions) -> Result<(), String> {
@@ -155,3 +457,285 @@ pub fn zip_dir(zip: &mut ZipWriter<File>, src_dir: &str, options: FileOptions) -
   Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在系统临时目录下创建测试用的项目目录,并创建指定的文件
+    fn project(name: &str, files: &[&str]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("deploy-tool-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn options(skip_tests: bool) -> BuildOptions {
+        BuildOptions {
+            skip_tests,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn command_prefers_wrapper_unless_build_command_is_set() {
+        let dir = project("wrapper", &[]);
+        let project_dir = dir.to_string_lossy();
+        assert_eq!(
+            BuildTool::Maven
+                .command(&project_dir, &options(false), &[])
+                .0,
+            "mvn"
+        );
+        assert_eq!(
+            BuildTool::Gradle
+                .command(&project_dir, &options(false), &[])
+                .0,
+            "gradle"
+        );
+
+        for wrapper in ["mvnw", "mvnw.cmd", "gradlew", "gradlew.bat"] {
+            fs::write(dir.join(wrapper), "").unwrap();
+        }
+        let (program, _) = BuildTool::Maven.command(&project_dir, &options(false), &[]);
+        assert!(
+            program.starts_with('.') && program.contains("mvnw"),
+            "{}",
+            program
+        );
+        let (program, _) = BuildTool::Gradle.command(&project_dir, &options(false), &[]);
+        assert!(
+            program.starts_with('.') && program.contains("gradlew"),
+            "{}",
+            program
+        );
+
+        let options = BuildOptions {
+            build_command: Some("/opt/maven/bin/mvn".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            BuildTool::Maven.command(&project_dir, &options, &[]).0,
+            "/opt/maven/bin/mvn"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn args(build_tool: BuildTool, options: &BuildOptions, source_dirs: &[&str]) -> Vec<String> {
+        let source_dirs: Vec<String> = source_dirs.iter().map(|dir| dir.to_string()).collect();
+        build_tool.command(".", options, &source_dirs).1
+    }
+
+    #[test]
+    fn command_skips_tests_and_appends_build_args() {
+        assert_eq!(
+            args(BuildTool::Maven, &options(false), &[]),
+            ["clean", "package"]
+        );
+        assert_eq!(
+            args(BuildTool::Maven, &options(true), &[]),
+            ["clean", "package", "-DskipTests"]
+        );
+        assert_eq!(
+            args(BuildTool::Gradle, &options(false), &[]),
+            ["clean", "test", "bootJar"]
+        );
+        assert_eq!(
+            args(BuildTool::Gradle, &options(true), &[]),
+            ["clean", "bootJar"]
+        );
+
+        let options = BuildOptions {
+            build_args: vec!["-P".to_string(), "prod".to_string()],
+            ..options(true)
+        };
+        assert_eq!(
+            args(BuildTool::Maven, &options, &["admin"]),
+            [
+                "clean",
+                "package",
+                "-DskipTests",
+                "-pl",
+                "admin",
+                "-am",
+                "-P",
+                "prod"
+            ]
+        );
+    }
+
+    #[test]
+    fn command_builds_only_selected_modules() {
+        assert_eq!(
+            args(
+                BuildTool::Maven,
+                &options(false),
+                &["admin", "services/client/"]
+            ),
+            ["clean", "package", "-pl", "admin,services/client", "-am"]
+        );
+        assert_eq!(
+            args(
+                BuildTool::Gradle,
+                &options(false),
+                &["admin", "services/client"]
+            ),
+            [
+                ":admin:clean",
+                ":admin:test",
+                ":admin:bootJar",
+                ":services:client:clean",
+                ":services:client:test",
+                ":services:client:bootJar",
+            ]
+        );
+        assert_eq!(
+            args(BuildTool::Gradle, &options(true), &["admin"]),
+            [":admin:clean", ":admin:bootJar"]
+        );
+        // 单模块项目的源码目录为项目根目录,构建整个项目
+        assert_eq!(
+            args(BuildTool::Maven, &options(true), &[".", "admin"]),
+            ["clean", "package", "-DskipTests"]
+        );
+    }
+
+    fn module(
+        name: &str,
+        source_dir: &str,
+        artifact: Option<&str>,
+        jar_name: Option<&str>,
+    ) -> Module {
+        Module {
+            name: name.to_string(),
+            source_dir: source_dir.to_string(),
+            artifact: artifact.map(str::to_string),
+            jar_name: jar_name.map(str::to_string),
+            remote_path: format!("/opt/apps/{}.jar", name),
+            port: None,
+            jvm_opts: None,
+            health_check: None,
+            log_file: None,
+        }
+    }
+
+    fn find(dir: &Path, module: &Module, build_tool: BuildTool) -> Result<String, String> {
+        find_artifact(&dir.to_string_lossy(), module, build_tool)
+    }
+
+    #[test]
+    fn maven_prefers_jar_name_then_pom_final_name() {
+        let dir = project(
+            "maven",
+            &["admin/target/admin.jar", "admin/target/other.jar"],
+        );
+        let admin = module("admin", "admin", None, Some("admin.jar"));
+        assert!(find(&dir, &admin, BuildTool::Maven)
+            .unwrap()
+            .ends_with("admin/target/admin.jar"));
+
+        fs::write(
+            dir.join("admin/pom.xml"),
+            "<project><artifactId>admin</artifactId><build><finalName>${project.artifactId}-app</finalName></build></project>",
+        )
+        .unwrap();
+        fs::write(dir.join("admin/target/admin-app.jar"), "").unwrap();
+        fs::remove_file(dir.join("admin/target/admin.jar")).unwrap();
+        assert!(find(&dir, &admin, BuildTool::Maven)
+            .unwrap()
+            .ends_with("admin/target/admin-app.jar"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_single_jar_and_ignores_auxiliary_jars() {
+        let dir = project(
+            "fallback",
+            &[
+                "target/app-1.0.jar",
+                "target/app-1.0-sources.jar",
+                "target/app-1.0.jar.original",
+            ],
+        );
+        let app = module("app", ".", None, None);
+        assert!(find(&dir, &app, BuildTool::Maven)
+            .unwrap()
+            .ends_with("target/app-1.0.jar"));
+
+        fs::write(dir.join("target/app-2.0.jar"), "").unwrap();
+        assert!(find(&dir, &app, BuildTool::Maven)
+            .unwrap_err()
+            .contains("多个编译产物"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn gradle_and_configured_artifacts() {
+        let dir = project(
+            "gradle",
+            &[
+                "admin/build/libs/admin-1.0.jar",
+                "admin/build/libs/admin-1.0-plain.jar",
+            ],
+        );
+        let admin = module("admin", "admin", None, None);
+        assert!(find(&dir, &admin, BuildTool::Gradle)
+            .unwrap()
+            .ends_with("build/libs/admin-1.0.jar"));
+
+        // 配置了artifact时只按它查找
+        let configured = module("admin", "admin", Some("dist/*.jar"), None);
+        let error = find(&dir, &configured, BuildTool::Gradle).unwrap_err();
+        assert!(error.contains("dist/*.jar"), "{}", error);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn package_manager_follows_lockfile_precedence() {
+        let cases: [(&[&str], PackageManager); 6] = [
+            (
+                &["pnpm-lock.yaml", "yarn.lock", "package-lock.json"],
+                PackageManager::Pnpm,
+            ),
+            (&["yarn.lock", "bun.lockb"], PackageManager::Yarn),
+            (&["bun.lockb", "package-lock.json"], PackageManager::Bun),
+            (&["bun.lock"], PackageManager::Bun),
+            (&["package-lock.json"], PackageManager::Npm),
+            (&[], PackageManager::Npm),
+        ];
+        for (files, expected) in cases {
+            let dir = project("lockfile", files);
+            let resolved = PackageManager::Auto.resolve(&dir.to_string_lossy());
+            assert!(resolved == expected, "{:?}", files);
+            // 显式配置的包管理器不受锁文件影响
+            assert!(PackageManager::Yarn.resolve(&dir.to_string_lossy()) == PackageManager::Yarn);
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn install_args_use_frozen_lockfile() {
+        let dir = project("install", &[]);
+        let project_dir = dir.to_string_lossy().to_string();
+        // 没有package-lock.json时无法使用npm ci
+        assert_eq!(PackageManager::Npm.install_args(&project_dir), ["install"]);
+        fs::write(dir.join("package-lock.json"), "").unwrap();
+        assert_eq!(PackageManager::Npm.install_args(&project_dir), ["ci"]);
+        for manager in [
+            PackageManager::Pnpm,
+            PackageManager::Yarn,
+            PackageManager::Bun,
+        ] {
+            assert_eq!(
+                manager.install_args(&project_dir),
+                ["install", "--frozen-lockfile"]
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use clap::{Arg, ArgAction, Command};
+
+/// 构建命令行参数定义
+pub fn build_cli() -> Command {
+    let mut command = Command::new("deploy-tool")
+        .version("1.3")
+        .author("士钰 <zhoushiyu92@gmail.com>")
+        .about("一键部署Java和Vue项目,支持多环境部署,支持多模块部署")
+        // 兼容旧版本的平铺参数: deploy-tool -e dev -m admin / deploy-tool -v dev / deploy-tool --init-config
+        .arg(env_arg("部署后端服务环境，多个环境用逗号分隔 (例如: dev,prod)", false))
+        .arg(vue_arg("部署web端环境，多个环境用逗号分隔，环境:应用 只部署指定的web应用 (例如: dev,prod:admin)"))
+        .arg(model_arg("部署jar模块，多个模块用逗号分隔 (例如: admin,client,websocket)"))
+        .arg(
+            Arg::new("init-config")
+                .long("init-config")
+                .help("交互式创建配置文件,等同于init子命令")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(format_arg())
+        .arg(force_arg())
+        .arg(
+            Arg::new("project-dir")
+                .short('p')
+                .long("project-dir")
+                .value_name("PROJECT_DIR")
+                .help("指定项目根目录路径")
+                .global(true)
+                .required(false)
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("CONFIG")
+                .help("指定配置文件路径,默认依次查找项目目录、上级目录和$XDG_CONFIG_HOME/deploy-tool/下的deploy.toml")
+                .global(true)
+                .required(false),
+        )
+        .subcommand(
+            Command::new("deploy")
+                .about("编译并部署Java或Vue项目")
+                .arg(env_arg("部署后端服务环境，多个环境用逗号分隔 (例如: dev,prod)", false))
+                .arg(vue_arg("部署web端环境，多个环境用逗号分隔，环境:应用 只部署指定的web应用 (例如: dev,prod:admin)"))
+                .arg(model_arg("部署jar模块，多个模块用逗号分隔 (例如: admin,client,websocket)"))
+                .arg(
+                    Arg::new("skip-build")
+                        .long("skip-build")
+                        .help("不编译,部署已有的jar包和web端编译产物目录")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(artifact_arg()),
+        )
+        .subcommand(
+            Command::new("build")
+                .about("只编译不部署,不指定--vue时编译Java项目")
+                .arg(env_arg("使用这些后端环境的构建配置，多个环境用逗号分隔，不指定时使用[defaults] (例如: dev,prod)", false))
+                .arg(vue_arg("编译web端环境并压缩为zip，多个环境用逗号分隔，环境:应用 只编译指定的web应用 (例如: dev,prod:admin)"))
+                .arg(model_arg("只编译指定的jar模块及其依赖，需要同时指定-e (例如: admin,client)")),
+        )
+        .subcommand(
+            Command::new("upload")
+                .about("不重新编译,上传已有的编译产物并启动")
+                .arg(env_arg("上传后端服务环境，多个环境用逗号分隔 (例如: dev,prod)", false))
+                .arg(vue_arg("上传web端环境，多个环境用逗号分隔，环境:应用 只上传指定的web应用 (例如: dev,prod:admin)"))
+                .arg(model_arg("上传jar模块，多个模块用逗号分隔 (例如: admin,client,websocket)"))
+                .arg(artifact_arg()),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("查看各环境下模块的运行状态")
+                .arg(env_arg("查询的环境，多个环境用逗号分隔 (例如: dev,prod)", true))
+                .arg(model_arg("查询的jar模块，多个模块用逗号分隔 (例如: admin,client)"))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("以JSON格式输出")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("logs")
+                .about("查看模块的远程日志")
+                .arg(env_arg("查看日志的环境，多个环境用逗号分隔 (例如: dev,prod)", true))
+                .arg(model_arg("查看日志的jar模块，多个模块用逗号分隔 (例如: admin,client)"))
+                .arg(
+                    Arg::new("follow")
+                        .short('f')
+                        .long("follow")
+                        .help("持续跟踪日志输出")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("lines")
+                        .short('n')
+                        .long("lines")
+                        .value_name("LINES")
+                        .help("输出最后的行数")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("200"),
+                )
+                .arg(
+                    Arg::new("grep")
+                        .long("grep")
+                        .value_name("PATTERN")
+                        .help("只输出包含指定内容的行")
+                        .required(false),
+                ),
+        );
+
+    // 不上传新包,直接控制远程服务
+    for (name, about) in [
+        ("start", "启动远程服务器上已部署的模块"),
+        ("stop", "停止远程服务器上运行的模块"),
+        ("restart", "重启远程服务器上运行的模块"),
+        ("rollback", "回滚到上一次部署的jar包(.bak备份)并重启"),
+    ] {
+        command = command.subcommand(
+            Command::new(name)
+                .about(about)
+                .arg(env_arg("操作的环境，多个环境用逗号分隔 (例如: dev,prod)", true))
+                .arg(model_arg("操作的jar模块，多个模块用逗号分隔 (例如: admin,client)")),
+        );
+    }
+
+    // 加密和解密共用的参数
+    let secret_args = [
+        Arg::new("env")
+            .short('e')
+            .long("env")
+            .value_name("ENVIRONMENT")
+            .help("处理的环境，多个环境用逗号分隔，默认处理全部环境")
+            .value_delimiter(',')
+            .required(false),
+        Arg::new("key-file")
+            .long("key-file")
+            .value_name("KEY_FILE")
+            .help("密钥文件路径,默认使用$DEPLOY_TOOL_PASSPHRASE、$DEPLOY_TOOL_KEY_FILE或~/.config/deploy-tool/secret.key")
+            .required(false),
+    ];
+
+    command
+        .subcommand(
+            Command::new("init")
+                .about("检查项目中的pom.xml和package.json,交互式创建配置文件")
+                .arg(format_arg())
+                .arg(force_arg()),
+        )
+        .subcommand(
+            Command::new("secret")
+                .about("加密或解密配置文件中的密码")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("encrypt")
+                        .about("加密配置文件中的password和sudo_password字段")
+                        .args(secret_args.clone())
+                        .arg(
+                            Arg::new("value")
+                                .long("value")
+                                .help("只加密一个值并输出,不修改配置文件;值通过不回显的提示或标准输入读取")
+                                .action(ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("decrypt")
+                        .about("解密配置文件中enc:开头的字段")
+                        .args(secret_args),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("查看和检查配置文件")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("列出配置文件中的所有环境"))
+                .subcommand(Command::new("schema").about("输出配置文件的JSON Schema,用于编辑器补全和校验"))
+                .subcommand(
+                    Command::new("validate")
+                        .about("校验配置文件,不指定环境时校验所有环境")
+                        .arg(env_arg("校验的后端环境，多个环境用逗号分隔 (例如: dev,prod)", false))
+                        .arg(vue_arg("校验的web端环境，多个环境用逗号分隔 (例如: dev,prod)")),
+                )
+                .subcommand(
+                    Command::new("doctor")
+                        .about("校验配置文件并连接服务器检查java、部署目录、unzip和磁盘空间")
+                        .arg(env_arg("检查的后端环境，多个环境用逗号分隔 (例如: dev,prod)", false))
+                        .arg(vue_arg("检查的web端环境，多个环境用逗号分隔 (例如: dev,prod)")),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("输出指定环境合并defaults和extends后的最终配置")
+                        .arg(env_arg("查看的环境，多个环境用逗号分隔 (例如: dev,prod)", true))
+                        .arg(
+                            Arg::new("show-secrets")
+                                .long("show-secrets")
+                                .help("显示密码明文")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+}
+
+/// 后端环境参数
+fn env_arg(help: &'static str, required: bool) -> Arg {
+    Arg::new("env")
+        .short('e')
+        .long("env")
+        .value_name("ENVIRONMENT")
+        .help(help)
+        .value_delimiter(',')
+        .required(required)
+}
+
+/// web端环境参数
+fn vue_arg(help: &'static str) -> Arg {
+    Arg::new("vue")
+        .short('v')
+        .long("vue")
+        .value_name("ENVIRONMENT")
+        .help(help)
+        .value_delimiter(',')
+        .required(false)
+}
+
+/// jar模块参数
+fn model_arg(help: &'static str) -> Arg {
+    Arg::new("model")
+        .short('m')
+        .long("model")
+        .value_name("MODEL")
+        .help(help)
+        .value_delimiter(',')
+        .required(false)
+}
+
+/// 创建的配置文件格式参数
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .value_name("FORMAT")
+        .help("创建的配置文件格式,默认按--config的扩展名确定,否则为toml")
+        .value_parser(["toml", "yaml", "json"])
+        .required(false)
+}
+
+/// 覆盖已有配置文件参数
+fn force_arg() -> Arg {
+    Arg::new("force")
+        .long("force")
+        .help("覆盖已存在的配置文件")
+        .action(ArgAction::SetTrue)
+}
+
+/// 指定模块编译产物参数
+fn artifact_arg() -> Arg {
+    Arg::new("artifact")
+        .long("artifact")
+        .value_name("MODULE=PATH")
+        .help("部署指定的jar包,不再编译,未指定-m时只部署这些模块 (例如: admin=build/admin.jar)")
+        .value_delimiter(',')
+        .action(ArgAction::Append)
+        .required(false)
+}
@@ -1,103 +1,964 @@
+use crate::build::{BuildTool, PackageManager};
+use crate::secret::decrypt_secrets;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::env;
+use std::path::{Path, PathBuf};
 use serde_json::Value;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// 默认配置文件名,不含扩展名
+pub const CONFIG_FILE_STEM: &str = "deploy";
+/// 查找配置文件时依次尝试的扩展名
+const CONFIG_EXTENSIONS: [&str; 4] = ["toml", "yaml", "yml", "json"];
+
+/// 配置文件格式,按扩展名识别
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// 按扩展名识别格式,.yaml/.yml为YAML,.json为JSON,其他为TOML
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    /// 解析为TOML表,YAML和JSON与TOML共用同一套合并和校验逻辑
+    pub fn parse(self, content: &str) -> Result<toml::Table, String> {
+        match self {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::from_str(content)
+                .map_err(|e| e.to_string())
+                .and_then(structured_to_table),
+            ConfigFormat::Json => serde_json::from_str(content)
+                .map_err(|e| e.to_string())
+                .and_then(structured_to_table),
+        }
+        .map_err(|e| format!("解析配置文件失败: {}", e))
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::to_string(value).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::to_string_pretty(value)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("序列化配置失败: {}", e))
+    }
+}
+
+/// YAML/JSON转换为TOML表,TOML没有null,值为null(YAML中的~)的字段视为未配置
+fn structured_to_table(mut value: serde_json::Value) -> Result<toml::Table, String> {
+    if !value.is_object() {
+        return Err("配置文件的顶层必须是对象".to_string());
+    }
+    remove_nulls(&mut value, "")?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+fn remove_nulls(value: &mut serde_json::Value, path: &str) -> Result<(), String> {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, item| !item.is_null());
+            for (name, item) in map.iter_mut() {
+                let path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+                remove_nulls(item, &path)?;
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                if item.is_null() {
+                    return Err(format!("{}[{}]的值不能为null", path, i));
+                }
+                remove_nulls(item, &format!("{}[{}]", path, i))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct DeployConfig {
     /// 服务器地址
     pub server: String,
     /// 用户名
     pub username: String,
     /// 密码
+    #[serde(default)]
     pub password: String,
+    /// 从指定的环境变量读取密码,配置后覆盖password (例如: PROD_SSH_PASS)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
     /// java路径
     pub java_path: String,
     /// 远程基础路径
     pub remote_base_path: String,
-    /// jar文件 (可以是字符串或字符串数组)
-    pub jar_files: Value,
+    /// jar文件 (可以是字符串或字符串数组,支持glob,例如admin-*.jar),是modules的简写形式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jar_files: Option<JarFiles>,
     /// vue打包执行命令脚本
+    #[serde(default)]
     pub scripts: String,
     /// vue编译产物输出目录
+    #[serde(default)]
     pub output_dir: String,
+    /// web端包管理器 (npm/pnpm/yarn/bun),auto时按项目中的锁文件识别
+    #[serde(default)]
+    pub package_manager: PackageManager,
+    /// 构建web端前先按锁文件安装依赖 (npm ci或install --frozen-lockfile)
+    #[serde(default)]
+    pub install: bool,
+    /// web端项目目录,相对项目根目录 (例如: web),默认为项目根目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_dir: Option<String>,
+    /// web端部署目录 (例如: /usr/share/nginx/html/admin),默认为{remote_base_path}/{output_dir}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_remote_path: Option<String>,
+    /// web端应用配置,配置后忽略scripts、output_dir和web_remote_path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub web_apps: Vec<WebAppConfig>,
+    /// 是否通过sudo执行远程的移动、杀进程、启动和解压命令
+    #[serde(default)]
+    pub sudo: bool,
+    /// 启动和停止服务时sudo切换到的运行用户 (sudo -u),文件操作仍以root执行,配置后自动启用sudo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
+    /// sudo密码,不配置则要求服务器上为免密sudo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sudo_password: Option<String>,
+    /// 健康检查地址,在服务器上通过curl访问 (例如: http://127.0.0.1:8080/actuator/health)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<String>,
+    /// 模块日志文件路径,{module}会替换为模块名称 (例如: /opt/apps/logs/{module}.log)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+    /// 模块配置,配置后忽略jar_files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<ModuleConfig>,
+    /// Java项目的构建工具,auto时按项目中的pom.xml或build.gradle(.kts)/gradlew识别
+    #[serde(default)]
+    pub build_tool: BuildTool,
+    /// 构建程序,默认优先使用项目中的mvnw/gradlew,否则为mvn/gradle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// 追加的构建参数 (例如: ["-P", "prod", "-T", "1C", "-s", "settings.xml"])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub build_args: Vec<String>,
+    /// 构建时跳过测试 (Maven: -DskipTests, Gradle: 不执行test任务)
+    #[serde(default)]
+    pub skip_tests: bool,
+}
+
+/// jar_files简写: 单模块项目为字符串,多模块项目为字符串数组
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum JarFiles {
+    /// 单模块项目,jar包位于target/下
+    Single(String),
+    /// 多模块项目,jar包位于{module}/target/下
+    Multiple(Vec<String>),
 }
 
-#[derive(Serialize, Deserialize)]
+/// [[environments.X.modules]] 模块配置
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// 模块名称,用于-m筛选,远程jar包名为{name}.jar
+    pub name: String,
+    /// 模块源码目录,相对项目根目录,默认与模块名称相同
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_dir: Option<String>,
+    /// 编译产物路径,相对模块源码目录,支持glob (例如: target/admin-*.jar),默认为target/{name}.jar,Gradle项目为build/libs/{name}*.jar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    /// 远程部署目录,默认为remote_base_path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_dir: Option<String>,
+    /// 服务端口,启动时传入--server.port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// JVM参数 (例如: -Xms512m -Xmx1g)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jvm_opts: Option<String>,
+    /// 健康检查地址,默认使用环境的health_check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<String>,
+    /// 日志文件路径,默认使用环境的log_file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+}
+
+/// [[environments.X.web_apps]] web端应用配置
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct WebAppConfig {
+    /// 应用名称,用于-v 环境:应用 筛选
+    pub name: String,
+    /// 应用目录,相对项目根目录,默认为环境的web_dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_dir: Option<String>,
+    /// 打包脚本,默认为环境的scripts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<String>,
+    /// 编译产物输出目录,相对应用目录,默认为环境的output_dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// 远程部署目录,上传时会先清空该目录,默认为{remote_base_path}/{name}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_path: Option<String>,
+}
+
+/// 合并了环境配置后的web端应用
+#[derive(Clone)]
+pub struct WebApp {
+    /// 应用名称
+    pub name: String,
+    /// 应用目录,相对项目根目录,为空时为项目根目录
+    pub web_dir: Option<String>,
+    /// 打包脚本
+    pub scripts: String,
+    /// 编译产物输出目录,相对应用目录
+    pub output_dir: String,
+    /// 远程部署目录
+    pub remote_path: String,
+}
+
+impl WebApp {
+    /// 应用的本地目录
+    pub fn project_dir(&self, project_dir: &str) -> String {
+        web_project_dir(project_dir, self.web_dir.as_deref())
+    }
+}
+
+/// web端项目目录,web_dir为相对路径时位于项目根目录下,未配置时为项目根目录
+fn web_project_dir(project_dir: &str, web_dir: Option<&str>) -> String {
+    match web_dir {
+        Some(web_dir) if Path::new(web_dir).is_absolute() => web_dir.to_string(),
+        Some(web_dir) => format!("{}/{}", project_dir, web_dir.trim_end_matches('/')),
+        None => project_dir.to_string(),
+    }
+}
+
+/// web端远程部署目录,配置了路径时去掉末尾的/,否则为{remote_base_path}/{name}
+fn web_remote_path(configured: Option<&str>, remote_base_path: &str, name: &str) -> String {
+    configured
+        .map(|path| path.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| format!("{}/{}", remote_base_path, name))
+}
+
+/// 合并了环境配置和简写形式后的模块
+#[derive(Clone)]
+pub struct Module {
+    /// 模块名称
+    pub name: String,
+    /// 模块源码目录,相对项目根目录
+    pub source_dir: String,
+    /// 配置的编译产物路径,相对模块源码目录,可能是glob,未配置时由构建工具决定
+    pub artifact: Option<String>,
+    /// jar_files中配置的jar包名,可能是glob
+    pub jar_name: Option<String>,
+    /// 远程jar包完整路径
+    pub remote_path: String,
+    /// 服务端口
+    pub port: Option<u16>,
+    /// JVM参数
+    pub jvm_opts: Option<String>,
+    /// 健康检查地址
+    pub health_check: Option<String>,
+    /// 日志文件路径,{module}已替换为模块名称
+    pub log_file: Option<String>,
+}
+
+/// deploy-tool配置文件
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(title = "deploy-tool", deny_unknown_fields)]
 pub struct Environments {
+    /// 所有环境共用的默认配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<EnvironmentSchema>")]
+    pub defaults: Option<toml::Table>,
+    /// 各环境的配置
+    #[schemars(with = "HashMap<String, EnvironmentSchema>")]
     pub environments: HashMap<String, DeployConfig>,
 }
 
+impl Environments {
+    /// 由配置类型生成JSON Schema,用于编辑器补全和校验
+    pub fn json_schema() -> Result<String, String> {
+        serde_json::to_string_pretty(&schemars::schema_for!(Environments))
+            .map_err(|e| format!("生成JSON Schema失败: {}", e))
+    }
+}
+
+/// 环境配置的JSON Schema: 字段可以来自[defaults]或extends,因此都不是必填,并允许extends
+struct EnvironmentSchema;
+
+impl JsonSchema for EnvironmentSchema {
+    fn schema_name() -> Cow<'static, str> {
+        "EnvironmentConfig".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = DeployConfig::json_schema(generator);
+        schema.remove("required");
+        if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert(
+                "extends".to_string(),
+                serde_json::json!({
+                    "type": "string",
+                    "description": "继承的环境名称,合并顺序为[defaults] < 继承的环境 < 当前环境",
+                }),
+            );
+        }
+        schema
+    }
+}
+
 impl DeployConfig {
-    pub fn create_springboot_config(path: &str) -> Result<(), String> {
-        let mut environments = HashMap::new();
-        environments.insert(
-            "dev".to_string(),
-            DeployConfig {
-                server: "192.168.31.60:22".to_string(),
-                username: "root".to_string(),
-                password: "lykj".to_string(),
-                java_path: "/opt/soft/zulu11/bin/java".to_string(),
-                remote_base_path: "/opt/xinxuan1v1".to_string(),
-                jar_files: serde_json::json!(["admin.jar", "client.jar", "websocket.jar"]),
-                scripts: "prod:test".to_string(),
-                output_dir: "dist-test".to_string(),
-            },
-        );
-        environments.insert(
-            "test".to_string(),
-            DeployConfig {
-                server: "test-server:22".to_string(),
-                username: "test-user".to_string(),
-                password: "test-password".to_string(),
-                java_path: "/usr/bin/java".to_string(),
-                remote_base_path: "/opt/test/apps".to_string(),
-                jar_files: serde_json::json!(["admin.jar", "client.jar", "websocket.jar"]),
-                scripts: "prod:test".to_string(),
-                output_dir: "dist-test".to_string(),
-            },
-        );
-        environments.insert(
-            "prod".to_string(),
-            DeployConfig {
-                server: "prod-server:22".to_string(),
-                username: "prod-user".to_string(),
-                password: "prod-password".to_string(),
-                java_path: "/usr/java/latest/bin/java".to_string(),
-                remote_base_path: "/opt/prod/apps".to_string(),
-                jar_files: serde_json::json!(["admin.jar", "client.jar", "websocket.jar"]),
-                scripts: "prod".to_string(),
-                output_dir: "dist".to_string(),
-            },
-        );
+    /// 获取环境下的所有模块,优先使用modules,未配置时由jar_files简写生成
+    pub fn modules(&self) -> Result<Vec<Module>, String> {
+        if !self.modules.is_empty() {
+            return Ok(self
+                .modules
+                .iter()
+                .map(|module| Module {
+                    name: module.name.clone(),
+                    source_dir: module.source_dir.clone().unwrap_or_else(|| module.name.clone()),
+                    artifact: module.artifact.clone(),
+                    jar_name: None,
+                    remote_path: format!(
+                        "{}/{}.jar",
+                        module.remote_dir.as_deref().unwrap_or(&self.remote_base_path),
+                        module.name
+                    ),
+                    port: module.port,
+                    jvm_opts: module.jvm_opts.clone(),
+                    health_check: module.health_check.clone().or_else(|| self.health_check.clone()),
+                    log_file: self.module_log_file(module.log_file.as_ref(), &module.name),
+                })
+                .collect());
+        }
 
-        let config = Environments { environments };
-        let toml_string = toml::to_string_pretty(&config)
-            .map_err(|e| format!("序列化配置失败: {}", e))?;
+        // 多模块项目的jar包位于{module}/下,单模块项目位于项目根目录下
+        let (jar_names, single) = match &self.jar_files {
+            Some(JarFiles::Multiple(jar_names)) => (jar_names.as_slice(), false),
+            Some(JarFiles::Single(jar_name)) => (std::slice::from_ref(jar_name), true),
+            None => return Err("未配置jar_files或modules".to_string()),
+        };
 
-        if let Some(parent) = Path::new(path).parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("创建配置目录失败: {}", e))?;
+        Ok(jar_names
+            .iter()
+            .map(|jar_name| {
+                let name = module_name(jar_name);
+                // jar包名是glob时(例如admin-*.jar),远程统一保存为{name}.jar
+                let remote_name = if is_glob(jar_name) {
+                    format!("{}.jar", name)
+                } else {
+                    jar_name.clone()
+                };
+                Module {
+                    source_dir: if single { ".".to_string() } else { name.clone() },
+                    artifact: None,
+                    jar_name: Some(jar_name.clone()),
+                    remote_path: format!("{}/{}", self.remote_base_path, remote_name),
+                    port: None,
+                    jvm_opts: None,
+                    health_check: self.health_check.clone(),
+                    log_file: self.module_log_file(None, &name),
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    /// 模块的日志文件路径,未单独配置时使用环境的log_file
+    fn module_log_file(&self, log_file: Option<&String>, name: &str) -> Option<String> {
+        log_file
+            .or(self.log_file.as_ref())
+            .map(|log_file| log_file.replace("{module}", name))
+    }
+
+    /// 获取环境下的所有web端应用,优先使用web_apps,未配置时由scripts和output_dir生成一个以output_dir命名的应用
+    pub fn web_apps(&self) -> Vec<WebApp> {
+        if !self.web_apps.is_empty() {
+            return self
+                .web_apps
+                .iter()
+                .map(|app| WebApp {
+                    name: app.name.clone(),
+                    web_dir: app.web_dir.clone().or_else(|| self.web_dir.clone()),
+                    scripts: app.scripts.clone().unwrap_or_else(|| self.scripts.clone()),
+                    output_dir: app.output_dir.clone().unwrap_or_else(|| self.output_dir.clone()),
+                    remote_path: web_remote_path(app.remote_path.as_deref(), &self.remote_base_path, &app.name),
+                })
+                .collect();
         }
 
-        fs::write(path, toml_string)
-            .map_err(|e| format!("写入配置文件失败: {}", e))?;
+        if self.scripts.is_empty() && self.output_dir.is_empty() {
+            return vec![];
+        }
+        vec![WebApp {
+            name: self.output_dir.clone(),
+            web_dir: self.web_dir.clone(),
+            scripts: self.scripts.clone(),
+            output_dir: self.output_dir.clone(),
+            remote_path: web_remote_path(self.web_remote_path.as_deref(), &self.remote_base_path, &self.output_dir),
+        }]
+    }
 
-        Ok(())
+    /// 环境中的构建配置
+    pub fn build_options(&self) -> BuildOptions {
+        BuildOptions {
+            build_tool: self.build_tool,
+            build_command: self.build_command.clone(),
+            build_args: self.build_args.clone(),
+            skip_tests: self.skip_tests,
+        }
+    }
+
+    /// 隐藏密码等敏感字段,用于输出配置
+    pub fn mask_secrets(&mut self) {
+        for secret in [&mut self.password].into_iter().chain(self.sudo_password.as_mut()) {
+            if !secret.is_empty() {
+                *secret = "******".to_string();
+            }
+        }
+    }
+
+    /// 序列化为指定格式的文本,带上environments.<environment>层级
+    pub fn to_text(&self, environment: &str, format: ConfigFormat) -> Result<String, String> {
+        let config = Environments {
+            defaults: None,
+            environments: HashMap::from([(environment.to_string(), self.clone())]),
+        };
+        format.serialize(&config)
     }
 
     pub fn from_file(config_path: &str, environment: &str) -> Result<Self, String> {
+        RawEnvironments::from_file(config_path)?
+            .resolve(environment)?
+            .resolve_env_vars()
+            .map_err(|e| format!("{}环境配置错误: {}", environment, e))
+    }
+
+    /// 替换所有字符串字段中的${VAR}和${VAR:-default},解密enc:开头的加密值,并从password_env读取密码
+    pub fn resolve_env_vars(self) -> Result<Self, String> {
+        let value = serde_json::to_value(&self).map_err(|e| format!("序列化配置失败: {}", e))?;
+        let value = decrypt_secrets(interpolate_value(value)?)?;
+        let mut config: DeployConfig = serde_json::from_value(value)
+            .map_err(|e| format!("解析配置失败: {}", e))?;
+
+        if let Some(name) = &config.password_env {
+            config.password = env::var(name)
+                .map_err(|_| format!("password_env指定的环境变量 {} 未设置", name))?;
+        }
+        Ok(config)
+    }
+
+    /// 获取配置文件中所有环境名称,按名称排序
+    pub fn environment_names(config_path: &str) -> Result<Vec<String>, String> {
+        Ok(RawEnvironments::from_file(config_path)?.names())
+    }
+}
+
+/// Java项目的构建配置,一次构建供所有环境共用
+#[derive(Clone, Default, PartialEq, Deserialize)]
+pub struct BuildOptions {
+    #[serde(default)]
+    pub build_tool: BuildTool,
+    #[serde(default)]
+    pub build_command: Option<String>,
+    #[serde(default)]
+    pub build_args: Vec<String>,
+    #[serde(default)]
+    pub skip_tests: bool,
+}
+
+impl BuildOptions {
+    /// 读取构建配置: 指定了环境时各环境的配置必须一致,否则读取[defaults],没有配置文件时使用默认值
+    pub fn load(config_path: &str, environments: &[String]) -> Result<Self, String> {
+        if environments.is_empty() {
+            if !Path::new(config_path).is_file() {
+                return Ok(BuildOptions::default());
+            }
+            let defaults = RawEnvironments::from_file(config_path)?.defaults.unwrap_or_default();
+            return toml::Value::Table(defaults)
+                .try_into()
+                .map_err(|e| format!("解析defaults中的构建配置失败: {}", e));
+        }
+
+        let mut options: Option<(String, BuildOptions)> = None;
+        for env in environments {
+            let current = DeployConfig::from_file(config_path, env)?.build_options();
+            match &options {
+                Some((first, first_options)) if *first_options != current => {
+                    return Err(format!(
+                        "{}环境和{}环境的构建配置不一致，无法共用一次构建，请分开部署",
+                        first, env
+                    ));
+                }
+                Some(_) => {}
+                None => options = Some((env.clone(), current)),
+            }
+        }
+        Ok(options.map(|(_, options)| options).unwrap_or_default())
+    }
+}
+
+/// 由jar文件名得到模块名称 (admin.jar -> admin, admin-*.jar -> admin)
+fn module_name(jar_name: &str) -> String {
+    let prefix = match jar_name.find(['*', '?', '[']) {
+        Some(index) => jar_name[..index].trim_end_matches(['-', '_', '.']),
+        None => jar_name,
+    };
+    prefix.split('.').next().unwrap_or_default().to_string()
+}
+
+/// 是否包含glob通配符
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// 未合并的原始配置文件内容
+pub struct RawEnvironments {
+    pub defaults: Option<toml::Table>,
+    pub environments: toml::Table,
+}
+
+impl RawEnvironments {
+    pub fn from_file(config_path: &str) -> Result<Self, String> {
         let config_content = fs::read_to_string(config_path)
-            .map_err(|e| format!("无法读取配置文件: {}", e))?;
-        
-        let environments: Environments = toml::from_str(&config_content)
-            .map_err(|e| format!("解析配置文件失败: {}", e))?;
-        
-        environments
-            .environments
-            .get(environment)
-            .cloned()
-            .ok_or_else(|| format!("环境 '{}' 未在配置文件中找到", environment))
-    }
-} 
\ No newline at end of file
+            .map_err(|e| format!("无法读取配置文件 {}: {}", config_path, e))?;
+        Self::parse(&config_content, ConfigFormat::from_path(config_path))
+    }
+
+    pub fn parse(config_content: &str, format: ConfigFormat) -> Result<Self, String> {
+        let mut root = format.parse(config_content)?;
+        let defaults = match root.remove("defaults") {
+            Some(toml::Value::Table(defaults)) => Some(defaults),
+            Some(_) => return Err("配置文件中defaults必须是表".to_string()),
+            None => None,
+        };
+        let environments = match root.remove("environments") {
+            Some(toml::Value::Table(environments)) => environments,
+            _ => return Err("配置文件中缺少environments表".to_string()),
+        };
+
+        Ok(RawEnvironments {
+            defaults,
+            environments,
+        })
+    }
+
+    /// 所有环境名称,按名称排序
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.environments.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 合并单个环境的[defaults]和extends,返回未解析的配置表
+    pub fn merged(&self, name: &str) -> Result<toml::Table, String> {
+        if !self.environments.contains_key(name) {
+            return Err(format!("环境 '{}' 未在配置文件中找到", name));
+        }
+        merge_environment(name, &self.environments, self.defaults.as_ref(), &mut vec![])
+    }
+
+    /// 合并并解析单个环境的配置
+    fn resolve(&self, name: &str) -> Result<DeployConfig, String> {
+        toml::Value::Table(self.merged(name)?)
+            .try_into()
+            .map_err(|e| format!("解析{}环境配置失败: {}", name, e))
+    }
+}
+
+/// 合并环境配置: [defaults] < extends继承的环境 < 当前环境
+fn merge_environment(
+    name: &str,
+    environments: &toml::Table,
+    defaults: Option<&toml::Table>,
+    chain: &mut Vec<String>,
+) -> Result<toml::Table, String> {
+    if chain.iter().any(|visited| visited == name) {
+        chain.push(name.to_string());
+        return Err(format!("环境继承存在循环: {}", chain.join(" -> ")));
+    }
+    chain.push(name.to_string());
+
+    let mut table = match environments.get(name) {
+        Some(toml::Value::Table(table)) => table.clone(),
+        Some(_) => return Err(format!("环境 '{}' 的配置必须是表", name)),
+        None => return Err(format!("环境 '{}' 继承的环境 '{}' 不存在", chain[0], name)),
+    };
+
+    let mut merged = match table.remove("extends") {
+        Some(toml::Value::String(parent)) => merge_environment(&parent, environments, defaults, chain)?,
+        Some(_) => return Err(format!("环境 '{}' 的extends必须是环境名称字符串", name)),
+        None => defaults.cloned().unwrap_or_default(),
+    };
+    merge_table(&mut merged, table);
+    Ok(merged)
+}
+
+/// 将overlay中的配置覆盖到base上,子表递归合并,其他值(包括数组)直接替换
+fn merge_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_table(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+
+/// 递归替换JSON值中所有字符串里的环境变量
+fn interpolate_value(value: Value) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(text) => Value::String(interpolate(&text)?),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(interpolate_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| Ok((key, interpolate_value(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other,
+    })
+}
+
+/// 替换字符串中的${VAR}和${VAR:-default},变量未设置或为空时使用默认值,$${ 表示字面量 ${
+fn interpolate(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(escaped) = after.strip_prefix("${") {
+            result.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        if !after.starts_with('{') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("环境变量引用缺少右括号: {}", text))?;
+        let expr = &after[1..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+
+        let value = match (env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => default.to_string(),
+            (Ok(value), _) => value,
+            (Err(_), Some(default)) => default.to_string(),
+            (Err(_), None) => return Err(format!("环境变量 {} 未设置", name)),
+        };
+        result.push_str(&value);
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 查找配置文件: 优先使用--config指定的路径,否则依次查找项目目录、项目目录的上级目录、
+/// $XDG_CONFIG_HOME/deploy-tool/,都不存在时返回项目目录下的默认路径
+pub fn find_config_path(explicit: Option<&str>, project_dir: &str) -> String {
+    if let Some(path) = explicit {
+        return path.to_string();
+    }
+
+    let project_dir = fs::canonicalize(project_dir).unwrap_or_else(|_| PathBuf::from(project_dir));
+    let mut dirs: Vec<PathBuf> = project_dir.ancestors().map(Path::to_path_buf).collect();
+    dirs.extend(user_config_dir());
+    // 同一目录下按toml、yaml、yml、json的顺序查找
+    dirs.into_iter()
+        .flat_map(|dir| {
+            CONFIG_EXTENSIONS.map(|ext| dir.join(format!("{}.{}", CONFIG_FILE_STEM, ext)))
+        })
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| {
+            init_config_path(None, project_dir.to_str().unwrap_or("."), ConfigFormat::Toml)
+        })
+}
+
+/// 创建示例配置文件的路径: --config指定的路径,否则为项目目录下对应格式的deploy.toml/deploy.yaml/deploy.json
+pub fn init_config_path(explicit: Option<&str>, project_dir: &str, format: ConfigFormat) -> String {
+    match explicit {
+        Some(path) => path.to_string(),
+        None => Path::new(project_dir)
+            .join(format!("{}.{}", CONFIG_FILE_STEM, format.extension()))
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+/// 用户级配置目录: $XDG_CONFIG_HOME/deploy-tool,未设置时为~/.config/deploy-tool
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("deploy-tool"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_replaces_variables_and_defaults() {
+        env::set_var("DEPLOY_TOOL_TEST_HOST", "10.0.0.1");
+        env::set_var("DEPLOY_TOOL_TEST_EMPTY", "");
+        env::remove_var("DEPLOY_TOOL_TEST_MISSING");
+
+        assert_eq!(
+            interpolate("${DEPLOY_TOOL_TEST_HOST}:22").unwrap(),
+            "10.0.0.1:22"
+        );
+        assert_eq!(
+            interpolate("${DEPLOY_TOOL_TEST_MISSING:-localhost}").unwrap(),
+            "localhost"
+        );
+        assert_eq!(
+            interpolate("${DEPLOY_TOOL_TEST_EMPTY:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(interpolate("plain $HOME text").unwrap(), "plain $HOME text");
+        assert_eq!(interpolate("$${LITERAL}").unwrap(), "${LITERAL}");
+    }
+
+    #[test]
+    fn interpolate_reports_missing_variables() {
+        env::remove_var("DEPLOY_TOOL_TEST_UNSET");
+        assert!(interpolate("${DEPLOY_TOOL_TEST_UNSET}")
+            .unwrap_err()
+            .contains("DEPLOY_TOOL_TEST_UNSET"));
+        assert!(interpolate("${DEPLOY_TOOL_TEST_UNSET")
+            .unwrap_err()
+            .contains("缺少右括号"));
+    }
+
+    #[test]
+    fn interpolate_value_walks_nested_strings() {
+        env::set_var("DEPLOY_TOOL_TEST_USER", "deploy");
+        let value = serde_json::json!({
+            "username": "${DEPLOY_TOOL_TEST_USER}",
+            "build_args": ["-P", "${DEPLOY_TOOL_TEST_USER}"],
+            "port": 8080,
+        });
+        let value = interpolate_value(value).unwrap();
+        assert_eq!(value["username"], "deploy");
+        assert_eq!(value["build_args"][1], "deploy");
+        assert_eq!(value["port"], 8080);
+    }
+
+    fn raw(content: &str) -> RawEnvironments {
+        RawEnvironments::parse(content, ConfigFormat::Toml).unwrap()
+    }
+
+    #[test]
+    fn merge_applies_defaults_then_parent_then_env() {
+        let environments = raw(r#"
+            [defaults]
+            username = "root"
+            java_path = "/usr/bin/java"
+            build_args = ["-P", "default"]
+
+            [environments.base]
+            server = "base:22"
+            java_path = "/opt/java/bin/java"
+
+            [environments.prod]
+            extends = "base"
+            username = "deploy"
+            build_args = ["-P", "prod"]
+            "#);
+        let merged = environments.merged("prod").unwrap();
+        assert_eq!(merged["server"].as_str(), Some("base:22"));
+        assert_eq!(merged["username"].as_str(), Some("deploy"));
+        assert_eq!(merged["java_path"].as_str(), Some("/opt/java/bin/java"));
+        // 数组整体替换,不拼接
+        assert_eq!(merged["build_args"].as_array().unwrap().len(), 2);
+        assert_eq!(merged["build_args"][1].as_str(), Some("prod"));
+        assert!(!merged.contains_key("extends"));
+    }
+
+    #[test]
+    fn merge_table_merges_nested_tables() {
+        let mut base: toml::Table = toml::from_str("[a]\nx = 1\ny = 2\n").unwrap();
+        let overlay: toml::Table = toml::from_str("[a]\ny = 3\nz = 4\n").unwrap();
+        merge_table(&mut base, overlay);
+        let a = base["a"].as_table().unwrap();
+        assert_eq!(a["x"].as_integer(), Some(1));
+        assert_eq!(a["y"].as_integer(), Some(3));
+        assert_eq!(a["z"].as_integer(), Some(4));
+    }
+
+    #[test]
+    fn merge_reports_cycles_and_missing_parents() {
+        let environments = raw(r#"
+            [environments.a]
+            extends = "b"
+            [environments.b]
+            extends = "a"
+            [environments.c]
+            extends = "missing"
+            "#);
+        assert!(environments
+            .merged("a")
+            .unwrap_err()
+            .contains("a -> b -> a"));
+        assert!(environments.merged("c").unwrap_err().contains("missing"));
+        assert!(environments.merged("unknown").is_err());
+    }
+
+    fn deploy_config(content: &str) -> DeployConfig {
+        toml::from_str(&format!(
+            "server = \"h:22\"\nusername = \"u\"\njava_path = \"/j\"\nremote_base_path = \"/opt/apps\"\n{}",
+            content
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn modules_fall_back_to_environment_log_file() {
+        let config = deploy_config(
+            r#"
+            log_file = "/opt/apps/logs/{module}.log"
+
+            [[modules]]
+            name = "admin"
+
+            [[modules]]
+            name = "client"
+            log_file = "/var/log/client/app.log"
+            "#,
+        );
+        let modules = config.modules().unwrap();
+        assert_eq!(modules[0].log_file.as_deref(), Some("/opt/apps/logs/admin.log"));
+        assert_eq!(modules[1].log_file.as_deref(), Some("/var/log/client/app.log"));
+
+        let modules = deploy_config("jar_files = [\"admin.jar\"]").modules().unwrap();
+        assert!(modules[0].log_file.is_none());
+    }
+
+    #[test]
+    fn web_apps_falls_back_to_single_app() {
+        assert!(deploy_config("").web_apps().is_empty());
+
+        let apps = deploy_config("scripts = \"build\"\noutput_dir = \"dist\"\nweb_dir = \"web\"")
+            .web_apps();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].name, "dist");
+        assert_eq!(apps[0].remote_path, "/opt/apps/dist");
+        assert_eq!(apps[0].project_dir("."), "./web");
+
+        let apps = deploy_config(
+            "scripts = \"build\"\noutput_dir = \"dist\"\nweb_remote_path = \"/usr/share/nginx/html/\"",
+        )
+        .web_apps();
+        assert_eq!(apps[0].remote_path, "/usr/share/nginx/html");
+        assert_eq!(apps[0].project_dir("."), ".");
+    }
+
+    #[test]
+    fn web_apps_inherit_environment_values() {
+        let apps = deploy_config(
+            r#"
+            scripts = "build"
+            output_dir = "dist"
+            web_dir = "web"
+
+            [[web_apps]]
+            name = "admin"
+            remote_path = "/usr/share/nginx/html/admin"
+
+            [[web_apps]]
+            name = "h5"
+            web_dir = "/abs/h5"
+            scripts = "build:h5"
+            output_dir = "out"
+            "#,
+        )
+        .web_apps();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].scripts, "build");
+        assert_eq!(apps[0].output_dir, "dist");
+        assert_eq!(apps[0].project_dir("/project"), "/project/web");
+        assert_eq!(apps[0].remote_path, "/usr/share/nginx/html/admin");
+        assert_eq!(apps[1].scripts, "build:h5");
+        assert_eq!(apps[1].output_dir, "out");
+        assert_eq!(apps[1].project_dir("/project"), "/abs/h5");
+        assert_eq!(apps[1].remote_path, "/opt/apps/h5");
+    }
+
+    #[test]
+    fn yaml_and_json_nulls_are_unset() {
+        let table = ConfigFormat::Yaml
+            .parse("defaults:\n  sudo_password: ~\n  run_as: null\n  username: root\nenvironments: {}\n")
+            .unwrap();
+        let defaults = table["defaults"].as_table().unwrap();
+        assert!(!defaults.contains_key("sudo_password"));
+        assert!(!defaults.contains_key("run_as"));
+        assert_eq!(defaults["username"].as_str(), Some("root"));
+
+        let error = ConfigFormat::Json
+            .parse(r#"{"environments": {"prod": {"build_args": ["-P", null]}}}"#)
+            .unwrap_err();
+        assert!(
+            error.contains("environments.prod.build_args[1]"),
+            "{}",
+            error
+        );
+        assert!(ConfigFormat::Json.parse("[]").is_err());
+    }
+}
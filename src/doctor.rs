@@ -0,0 +1,213 @@
+use crate::config::DeployConfig;
+use crate::upload::{connect, execute_privileged_query, execute_remote_query, shell_quote, SudoOptions};
+use ssh2::Session;
+use std::path::Path;
+use std::thread;
+
+/// 远程目录剩余空间低于此值(KB)时视为检查失败
+const MIN_FREE_KB: u64 = 1024 * 1024;
+
+/// 单项检查结果
+struct Check {
+    name: String,
+    /// 成功时为说明信息,失败时为失败原因
+    result: Result<String, String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, result: Result<String, String>) -> Self {
+        Check {
+            name: name.into(),
+            result,
+        }
+    }
+}
+
+/// 连接各环境的服务器,检查部署所需的远程环境是否就绪
+///
+/// `java_envs` 中的环境检查java_path,`vue_envs` 中的环境检查unzip,两者都为空时检查所有环境,
+/// 并按环境是否配置了模块或前端打包参数决定检查项。
+pub fn run_doctor(config_path: &str, java_envs: &[String], vue_envs: &[String]) -> Result<(), String> {
+    let check_all = java_envs.is_empty() && vue_envs.is_empty();
+    let environments = if check_all {
+        DeployConfig::environment_names(config_path)?
+    } else {
+        let mut names: Vec<String> = java_envs.iter().chain(vue_envs).cloned().collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+
+    // 每个环境一个线程并行检查
+    let mut handles = vec![];
+    for env in environments {
+        let config = DeployConfig::from_file(config_path, &env);
+        let java = check_all || java_envs.contains(&env);
+        let vue = vue_envs.contains(&env);
+
+        let handle = thread::spawn(move || {
+            let checks = match config {
+                Ok(config) => {
+                    let java = java && config.modules().map(|m| !m.is_empty()).unwrap_or(false);
+                    let vue = vue || (check_all && !config.web_apps().is_empty());
+                    check_env(&config, java, vue)
+                }
+                Err(e) => vec![Check::new("加载配置", Err(e))],
+            };
+            (env, checks)
+        });
+        handles.push(handle);
+    }
+
+    let mut failed = 0;
+    for handle in handles {
+        let (env, checks) = handle.join().unwrap();
+        println!("[{}]", env);
+        for check in checks {
+            match check.result {
+                Ok(detail) => println!("  ✓ {}: {}", check.name, detail),
+                Err(e) => {
+                    failed += 1;
+                    println!("  ✗ {}: {}", check.name, e);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("共{}项检查未通过", failed));
+    }
+    println!("所有检查均已通过");
+    Ok(())
+}
+
+/// 检查单个环境
+fn check_env(config: &DeployConfig, java: bool, vue: bool) -> Vec<Check> {
+    let sess = match connect(config) {
+        Ok(sess) => sess,
+        Err(e) => return vec![Check::new("SSH连接", Err(e))],
+    };
+    let mut checks = vec![Check::new(
+        "SSH连接",
+        Ok(format!("{}@{}", config.username, config.server)),
+    )];
+
+    // 与部署时执行的sudo命令一致: 文件操作以root执行,启动和停止服务时切换到run_as
+    let sudo = SudoOptions::from_config(config);
+    if sudo.enabled {
+        checks.push(Check::new("sudo", check_sudo(&sess, &sudo.as_root())));
+        if let Some(user) = &sudo.run_as {
+            checks.push(Check::new(format!("sudo -u {}", user), check_sudo(&sess, &sudo)));
+        }
+    }
+
+    if java {
+        checks.push(Check::new("java_path", check_java(&sess, &config.java_path)));
+    }
+
+    // 部署目录: remote_base_path、模块单独配置的目录以及各web端应用部署目录的上级目录
+    let mut remote_paths = vec![];
+    if java {
+        for module in config.modules().unwrap_or_default() {
+            remote_paths.push(module.remote_path);
+        }
+    }
+    if vue {
+        remote_paths.extend(config.web_apps().into_iter().map(|app| app.remote_path));
+    }
+    let mut dirs = vec![config.remote_base_path.clone()];
+    for remote_path in remote_paths {
+        if let Some(dir) = Path::new(&remote_path).parent() {
+            let dir = dir.to_string_lossy().to_string();
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    for dir in &dirs {
+        let result = check_dir(&sess, dir, &sudo.as_root());
+        let exists = result.is_ok();
+        checks.push(Check::new(format!("目录 {}", dir), result));
+        if exists {
+            checks.push(Check::new(format!("磁盘空间 {}", dir), check_disk(&sess, dir)));
+        }
+    }
+
+    if vue {
+        checks.push(Check::new(
+            "unzip",
+            // 解压时使用的是/usr/bin/unzip
+            run_test(&sess, "test -x /usr/bin/unzip", "服务器未安装/usr/bin/unzip,无法解压web端压缩包")
+                .map(|_| "/usr/bin/unzip".to_string()),
+        ));
+    }
+
+    checks
+}
+
+/// 执行test类命令,退出状态非0时返回指定的失败原因
+fn run_test(sess: &Session, command: &str, message: &str) -> Result<String, String> {
+    execute_remote_query(sess, command)
+        .map(|_| "正常".to_string())
+        .map_err(|_| message.to_string())
+}
+
+/// 检查能否按配置执行sudo
+fn check_sudo(sess: &Session, sudo: &SudoOptions) -> Result<String, String> {
+    execute_privileged_query(sess, sudo, "true")
+        .map(|_| "正常".to_string())
+        .map_err(|e| format!("无法执行sudo,请检查sudoers配置和sudo_password: {}", e))
+}
+
+/// 检查java程序存在且可执行,并返回版本信息
+fn check_java(sess: &Session, java_path: &str) -> Result<String, String> {
+    let java = shell_quote(java_path);
+    run_test(
+        sess,
+        &format!("test -x {}", java),
+        &format!("远程文件不存在或不可执行: {}", java_path),
+    )?;
+    // java -version输出到stderr
+    let version = execute_remote_query(sess, &format!("{} -version 2>&1 | head -n 1", java))
+        .unwrap_or_default();
+    Ok(version.trim().to_string())
+}
+
+/// 检查目录存在且可写,启用sudo时检查能否通过sudo以root写入
+fn check_dir(sess: &Session, dir: &str, sudo: &SudoOptions) -> Result<String, String> {
+    let quoted = shell_quote(dir);
+    run_test(
+        sess,
+        &format!("test -d {}", quoted),
+        &format!("远程目录不存在，或者路径错误: {}", dir),
+    )?;
+    if sudo.enabled {
+        return execute_privileged_query(sess, sudo, &format!("test -w {}", quoted))
+            .map(|_| "存在且可写 (通过sudo写入)".to_string())
+            .map_err(|_| format!("通过sudo没有写入权限: {}", dir));
+    }
+    run_test(
+        sess,
+        &format!("test -w {}", quoted),
+        &format!("当前用户没有写入权限: {}", dir),
+    )
+    .map(|_| "存在且可写".to_string())
+}
+
+/// 查询目录所在磁盘的剩余空间
+fn check_disk(sess: &Session, dir: &str) -> Result<String, String> {
+    let output = execute_remote_query(
+        sess,
+        &format!("df -Pk {} | tail -n 1 | awk '{{print $4}}'", shell_quote(dir)),
+    )
+    .map_err(|e| format!("查询磁盘空间失败: {}", e))?;
+    let free_kb: u64 = output
+        .trim()
+        .parse()
+        .map_err(|_| format!("无法解析磁盘空间: {}", output.trim()))?;
+    let free = format!("剩余 {:.1} GB", free_kb as f64 / 1024.0 / 1024.0);
+    if free_kb < MIN_FREE_KB {
+        return Err(format!("{},不足1GB", free));
+    }
+    Ok(free)
+}
@@ -0,0 +1,470 @@
+use crate::config::{ConfigFormat, JarFiles, ModuleConfig};
+use crate::maven::read_pom;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// 前端打包配置文件,用于识别输出目录
+const FRONTEND_CONFIG_FILES: [&str; 5] = [
+    "vite.config.ts",
+    "vite.config.js",
+    "vite.config.mts",
+    "vue.config.js",
+    "vue.config.ts",
+];
+
+/// 从pom.xml中识别出的可部署模块
+struct MavenModule {
+    /// 模块目录,相对项目根目录,单模块项目为"."
+    dir: String,
+    artifact_id: String,
+    /// build.finalName,未配置或无法解析时为空
+    final_name: Option<String>,
+    /// 是否使用了spring-boot-maven-plugin
+    spring_boot: bool,
+}
+
+/// 生成的配置文件,字段顺序即输出顺序
+#[derive(Serialize)]
+struct InitConfig {
+    defaults: InitDefaults,
+    environments: BTreeMap<String, InitEnvironment>,
+}
+
+/// 所有环境共用的配置
+#[derive(Serialize)]
+struct InitDefaults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    java_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jar_files: Option<JarFiles>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scripts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dir: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    modules: Vec<ModuleConfig>,
+}
+
+/// 单个环境的配置
+#[derive(Serialize)]
+struct InitEnvironment {
+    server: String,
+    username: String,
+    password_env: String,
+    remote_base_path: String,
+}
+
+/// 检查项目中的pom.xml和package.json,交互式生成配置文件
+pub fn run_init(project_dir: &str, config_path: &str, format: ConfigFormat, force: bool) -> Result<(), String> {
+    if Path::new(config_path).exists() && !force {
+        return Err(format!("配置文件已存在: {}，如需覆盖请使用--force", config_path));
+    }
+
+    let root = Path::new(project_dir);
+    let mut maven_modules = vec![];
+    collect_maven_modules(root, ".", &mut maven_modules);
+    // 有Spring Boot模块时只部署这些模块,其余视为依赖库
+    if maven_modules.iter().any(|module| module.spring_boot) {
+        maven_modules.retain(|module| module.spring_boot);
+    }
+    let npm_scripts = read_npm_scripts(root);
+
+    println!("项目目录: {}", project_dir);
+    for module in &maven_modules {
+        println!(
+            "  发现Maven模块: {} ({})",
+            module.dir,
+            module.final_name.as_deref().unwrap_or(&module.artifact_id)
+        );
+    }
+    if !npm_scripts.is_empty() {
+        println!("  发现package.json打包脚本: {}", npm_scripts.join(", "));
+    }
+    if maven_modules.is_empty() && npm_scripts.is_empty() {
+        println!("  未发现pom.xml或package.json中的打包配置");
+    }
+    println!();
+
+    let mut defaults = InitDefaults {
+        java_path: None,
+        jar_files: None,
+        scripts: None,
+        output_dir: None,
+        modules: vec![],
+    };
+    if !maven_modules.is_empty() || npm_scripts.is_empty() {
+        defaults.java_path = Some(prompt("远程服务器java程序路径", "/usr/bin/java")?);
+        let (jar_files, modules) = propose_modules(&maven_modules);
+        defaults.jar_files = jar_files;
+        defaults.modules = modules;
+    }
+    if !npm_scripts.is_empty() {
+        defaults.scripts = Some(prompt("web端打包脚本 (npm run 后面的名称)", &propose_script(&npm_scripts))?);
+        defaults.output_dir = Some(prompt("web端编译产物输出目录", &read_output_dir(root))?);
+    }
+
+    let project_name = fs::canonicalize(root)
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "app".to_string());
+
+    let mut environments = BTreeMap::new();
+    let names = prompt("环境名称，多个环境用逗号分隔", "dev")?;
+    for env in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        println!("[{}环境]", env);
+        let mut server = prompt_required("  服务器地址 (host或host:port)")?;
+        if !server.contains(':') {
+            server.push_str(":22");
+        }
+        let environment = InitEnvironment {
+            server,
+            username: prompt("  SSH用户名", "root")?,
+            password_env: prompt(
+                "  读取SSH密码的环境变量",
+                &format!("{}_SSH_PASS", env.to_uppercase().replace('-', "_")),
+            )?,
+            remote_base_path: prompt("  远程部署目录", &format!("/opt/{}", project_name))?,
+        };
+        environments.insert(env.to_string(), environment);
+    }
+    if environments.is_empty() {
+        return Err("至少需要一个环境".to_string());
+    }
+
+    let config = InitConfig {
+        defaults,
+        environments,
+    };
+    let content = format.serialize(&config)?;
+    if let Some(parent) = Path::new(config_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
+    }
+    fs::write(config_path, content).map_err(|e| format!("写入配置文件失败: {}", e))?;
+
+    println!();
+    println!("配置文件已创建: {}", config_path);
+    for (env, environment) in &config.environments {
+        println!("部署{}环境前请设置环境变量 {}", env, environment.password_env);
+    }
+    println!("也可以改为在配置文件中填写password后执行 deploy-tool secret encrypt 加密保存");
+    Ok(())
+}
+
+/// 由Maven模块生成jar_files,模块目录和jar包名不一致时生成modules
+fn propose_modules(maven_modules: &[MavenModule]) -> (Option<JarFiles>, Vec<ModuleConfig>) {
+    if let [module] = maven_modules {
+        if module.dir == "." && !module.final_name_has_dot() {
+            if let Some(final_name) = &module.final_name {
+                return (Some(JarFiles::Single(format!("{}.jar", final_name))), vec![]);
+            }
+        }
+    }
+
+    // jar_files简写要求jar包位于{jar包名}/target/下
+    let shorthand = !maven_modules.is_empty()
+        && maven_modules.iter().all(|module| {
+            module.final_name.as_deref() == Some(module.dir.as_str()) && !module.final_name_has_dot()
+        });
+    if shorthand {
+        let jar_names = maven_modules
+            .iter()
+            .map(|module| format!("{}.jar", module.dir))
+            .collect();
+        return (Some(JarFiles::Multiple(jar_names)), vec![]);
+    }
+
+    // 编译产物在部署时按finalName或target/*.jar查找,不需要配置artifact
+    let modules = maven_modules
+        .iter()
+        .map(|module| ModuleConfig {
+            name: module.artifact_id.clone(),
+            source_dir: (module.dir != module.artifact_id).then(|| module.dir.clone()),
+            ..Default::default()
+        })
+        .collect();
+    (None, modules)
+}
+
+impl MavenModule {
+    /// jar_files通过第一个"."截取模块名称,名称中带"."时不能使用简写
+    fn final_name_has_dot(&self) -> bool {
+        self.final_name.as_deref().is_some_and(|name| name.contains('.'))
+    }
+}
+
+/// 递归读取pom.xml,聚合模块继续读取<modules>中的子模块
+fn collect_maven_modules(root: &Path, dir: &str, modules: &mut Vec<MavenModule>) {
+    let pom = match read_pom(&root.join(dir)) {
+        Ok(Some(pom)) => pom,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    for name in &pom.modules {
+        let path = if dir == "." {
+            name.to_string()
+        } else {
+            format!("{}/{}", dir, name)
+        };
+        collect_maven_modules(root, &path, modules);
+    }
+
+    if pom.packaging != "jar" || pom.artifact_id.is_empty() {
+        return;
+    }
+    modules.push(MavenModule {
+        dir: dir.to_string(),
+        artifact_id: pom.artifact_id,
+        final_name: pom.final_name,
+        spring_boot: pom.spring_boot,
+    });
+}
+
+/// 读取package.json中以build开头的脚本
+fn read_npm_scripts(root: &Path) -> Vec<String> {
+    let package: Value = match fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        Some(package) => package,
+        None => return vec![],
+    };
+    package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+        .map(|scripts| {
+            scripts
+                .keys()
+                .filter(|name| name.starts_with("build"))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 优先使用build:prod,其次build
+fn propose_script(scripts: &[String]) -> String {
+    ["build:prod", "build"]
+        .iter()
+        .find(|name| scripts.iter().any(|script| script == *name))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| scripts[0].clone())
+}
+
+/// 从vite或vue-cli配置中读取outDir/outputDir,默认为dist
+fn read_output_dir(root: &Path) -> String {
+    for file in FRONTEND_CONFIG_FILES {
+        let content = match fs::read_to_string(root.join(file)) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for key in ["outDir", "outputDir"] {
+            if let Some(dir) = content.find(key).and_then(|index| quoted_value(&content[index + key.len()..])) {
+                return dir;
+            }
+        }
+    }
+    "dist".to_string()
+}
+
+/// 读取 `: 'dist'` 形式的字符串值
+fn quoted_value(text: &str) -> Option<String> {
+    let text = text.trim_start().strip_prefix(':')?.trim_start();
+    let quote = text.chars().next().filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let rest = &text[1..];
+    rest.find(quote).map(|end| rest[..end].to_string())
+}
+
+/// 读取一行输入,直接回车时使用默认值
+fn prompt(label: &str, default: &str) -> Result<String, String> {
+    if default.is_empty() {
+        print!("{}: ", label);
+    } else {
+        print!("{} [{}]: ", label, default);
+    }
+    io::stdout().flush().map_err(|e| format!("输出失败: {}", e))?;
+
+    let mut line = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("读取输入失败: {}", e))?;
+    if read == 0 {
+        // 输入结束时换行,避免后续输出接在提示后面
+        println!();
+        if default.is_empty() {
+            return Err(format!("未输入{}", label.trim()));
+        }
+    }
+    let value = line.trim();
+    Ok(if value.is_empty() { default.to_string() } else { value.to_string() })
+}
+
+/// 读取必填的输入,为空时重新提示
+fn prompt_required(label: &str) -> Result<String, String> {
+    loop {
+        let value = prompt(label, "")?;
+        if !value.is_empty() {
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    /// 在系统临时目录下创建测试用的项目目录,并写入指定的文件内容
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("deploy-tool-init-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, content) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn maven_module(dir: &str, artifact_id: &str, final_name: Option<&str>) -> MavenModule {
+        MavenModule {
+            dir: dir.to_string(),
+            artifact_id: artifact_id.to_string(),
+            final_name: final_name.map(str::to_string),
+            spring_boot: true,
+        }
+    }
+
+    #[test]
+    fn single_module_with_final_name_uses_jar_files() {
+        let (jar_files, modules) = propose_modules(&[maven_module(".", "app", Some("app"))]);
+        assert!(matches!(jar_files, Some(JarFiles::Single(name)) if name == "app.jar"));
+        assert!(modules.is_empty());
+
+        // 未配置finalName或finalName带"."时生成modules,编译产物在部署时查找
+        for final_name in [None, Some("app-1.0")] {
+            let (jar_files, modules) = propose_modules(&[maven_module(".", "app", final_name)]);
+            assert!(jar_files.is_none());
+            assert_eq!(modules.len(), 1);
+            assert_eq!(modules[0].name, "app");
+            assert_eq!(modules[0].source_dir.as_deref(), Some("."));
+            assert!(modules[0].artifact.is_none());
+        }
+    }
+
+    #[test]
+    fn modules_named_after_their_dir_use_shorthand() {
+        let (jar_files, modules) = propose_modules(&[
+            maven_module("admin", "admin-app", Some("admin")),
+            maven_module("client", "client", Some("client")),
+        ]);
+        assert!(
+            matches!(jar_files, Some(JarFiles::Multiple(names)) if names == ["admin.jar", "client.jar"])
+        );
+        assert!(modules.is_empty());
+    }
+
+    #[test]
+    fn other_modules_are_listed_explicitly() {
+        let (jar_files, modules) = propose_modules(&[
+            maven_module("admin", "admin", Some("admin")),
+            // finalName带"."时jar_files无法截取出模块名称
+            maven_module("api.v2", "api", Some("api.v2")),
+            maven_module("services/client", "client", None),
+        ]);
+        assert!(jar_files.is_none());
+        assert_eq!(modules.len(), 3);
+        // 模块名称使用artifactId
+        assert_eq!(modules[0].name, "admin");
+        assert!(modules[0].source_dir.is_none());
+        assert_eq!(modules[1].name, "api");
+        assert_eq!(modules[1].source_dir.as_deref(), Some("api.v2"));
+        assert_eq!(modules[2].name, "client");
+        assert_eq!(modules[2].source_dir.as_deref(), Some("services/client"));
+        assert!(modules.iter().all(|module| module.artifact.is_none()));
+    }
+
+    #[test]
+    fn collects_jar_modules_and_resolves_final_name() {
+        let dir = project(
+            "maven",
+            &[
+                (
+                    "pom.xml",
+                    "<project><artifactId>parent</artifactId><packaging>pom</packaging>\
+                     <modules><module>admin</module><module>client</module><module>lib</module></modules></project>",
+                ),
+                (
+                    "admin/pom.xml",
+                    "<project><artifactId>admin</artifactId>\
+                     <build><finalName>${project.artifactId}-app</finalName></build></project>",
+                ),
+                (
+                    "client/pom.xml",
+                    "<project><artifactId>client</artifactId>\
+                     <build><finalName>${unknown.property}</finalName></build></project>",
+                ),
+                ("lib/pom.xml", "<project><artifactId>lib</artifactId><packaging>pom</packaging></project>"),
+            ],
+        );
+        let mut modules = vec![];
+        collect_maven_modules(&dir, ".", &mut modules);
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].dir, "admin");
+        assert_eq!(modules[0].final_name.as_deref(), Some("admin-app"));
+        // 含有无法解析的属性时按未配置finalName处理
+        assert_eq!(modules[1].dir, "client");
+        assert!(modules[1].final_name.is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn quoted_value_reads_first_string_after_colon() {
+        assert_eq!(quoted_value(": 'dist'").as_deref(), Some("dist"));
+        assert_eq!(
+            quoted_value(":\"build/web\", other: 'x'").as_deref(),
+            Some("build/web")
+        );
+        assert_eq!(quoted_value(" : `out`").as_deref(), Some("out"));
+        assert!(quoted_value(" = 'dist'").is_none());
+        assert!(quoted_value(": dist").is_none());
+        assert!(quoted_value(": 'dist").is_none());
+    }
+
+    #[test]
+    fn read_output_dir_checks_vite_and_vue_configs() {
+        let dir = project(
+            "vite",
+            &[(
+                "vite.config.ts",
+                "export default defineConfig({ build: { outDir: 'build/web' } })",
+            )],
+        );
+        assert_eq!(read_output_dir(&dir), "build/web");
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = project(
+            "vue",
+            &[(
+                "vue.config.js",
+                "module.exports = { outputDir: \"public/app\" }",
+            )],
+        );
+        assert_eq!(read_output_dir(&dir), "public/app");
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = project("default", &[("vite.config.js", "export default {}")]);
+        assert_eq!(read_output_dir(&dir), "dist");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
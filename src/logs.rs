@@ -0,0 +1,109 @@
+use crate::config::DeployConfig;
+use crate::upload::{connect, shell_quote};
+use std::io::{BufRead, BufReader};
+use std::thread;
+
+/// 日志查看参数
+#[derive(Clone)]
+pub struct LogOptions {
+    /// 输出最后的行数
+    pub lines: u32,
+    /// 是否持续跟踪日志
+    pub follow: bool,
+    /// 过滤关键字
+    pub grep: Option<String>,
+}
+
+/// 查看各环境下模块的远程日志,多个模块同时输出时每行带上[环境/模块]前缀
+pub fn tail_logs(
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+    options: &LogOptions,
+) -> Result<(), String> {
+    let mut handles = vec![];
+
+    for env in environments {
+        let config = match DeployConfig::from_file(config_path, env) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("加载{}环境配置失败: {}", env, e);
+                continue;
+            }
+        };
+
+        for module in config.modules()? {
+            if !models.is_empty() && !models.contains(&module.name) {
+                continue;
+            }
+            let log_path = match module.log_file {
+                Some(log_path) => log_path,
+                None => {
+                    eprintln!("{}环境的{}模块未配置log_file，无法查看日志", env, module.name);
+                    continue;
+                }
+            };
+
+            let config = config.clone();
+            let options = options.clone();
+            let prefix = format!("[{}/{}]", env, module.name);
+
+            let handle = thread::spawn(move || {
+                if let Err(e) = stream_log(&config, &log_path, &prefix, &options) {
+                    eprintln!("{} 读取日志失败: {}", prefix, e);
+                }
+            });
+            handles.push(handle);
+        }
+    }
+
+    if handles.is_empty() {
+        return Err("没有可查看日志的模块".to_string());
+    }
+
+    // 等待所有线程完成
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    Ok(())
+}
+
+/// 通过SSH通道执行tail并逐行输出
+fn stream_log(
+    config: &DeployConfig,
+    log_path: &str,
+    prefix: &str,
+    options: &LogOptions,
+) -> Result<(), String> {
+    let sess = connect(config)?;
+    let mut channel = sess
+        .channel_session()
+        .map_err(|e| format!("创建SSH通道失败: {}", e))?;
+
+    // 合并标准错误,文件不存在等提示也能看到
+    let mut command = format!(
+        "tail -n {} {} {} 2>&1",
+        options.lines,
+        if options.follow { "-F" } else { "" },
+        shell_quote(log_path)
+    );
+    if let Some(pattern) = &options.grep {
+        command.push_str(&format!(" | grep --line-buffered -- {}", shell_quote(pattern)));
+    }
+
+    channel
+        .exec(&command)
+        .map_err(|e| format!("执行远程命令失败: {}", e))?;
+
+    // 按字节读取,非UTF-8的日志(例如GBK编码)替换无效字符后继续输出
+    let reader = BufReader::new(&mut channel);
+    for line in reader.split(b'\n').map_while(Result::ok) {
+        let line = String::from_utf8_lossy(&line);
+        println!("{} {}", prefix, line.trim_end_matches('\r'));
+    }
+
+    channel
+        .wait_close()
+        .map_err(|e| format!("等待通道关闭失败: {}", e))?;
+    Ok(())
+}
@@ -1,145 +1,358 @@
 mod build;
+mod cli;
 mod config;
+mod doctor;
+mod init;
+mod logs;
+mod maven;
+mod secret;
+mod service;
+mod status;
 mod upload;
-
-use build::{build_java_project, build_vue_project, zip_dir};
-use clap::{Arg, Command};
-use config::DeployConfig;
+mod validate;
+
+use build::{build_java_project, build_vue_project, find_artifact, install_vue_dependencies, zip_dir, PackageManager};
+use clap::ArgMatches;
+use config::{
+    find_config_path, init_config_path, BuildOptions, ConfigFormat, DeployConfig, Environments, Module,
+    WebApp,
+};
+use doctor::run_doctor;
+use init::run_init;
+use logs::{tail_logs, LogOptions};
+use secret::{convert_config_secrets, encrypt_single_value};
+use service::{control_services, ServiceAction};
+use status::show_status;
+use std::collections::HashMap;
 use std::fs::File;
+use std::path::Path;
 
 use std::thread;
 use std::time::{Duration, Instant};
 use upload::{upload_file, upload_and_run_jar};
+use validate::validate_config;
 
 use zip::CompressionMethod;
 use zip::{write::FileOptions, ZipWriter};
-use serde_json::Value;
+
+/// 部署流程包含的步骤
+#[derive(Clone, Copy, PartialEq)]
+enum Steps {
+    /// 只编译
+    Build,
+    /// 只上传已有的编译产物
+    Upload,
+    /// 编译并上传
+    BuildAndUpload,
+}
+
+impl Steps {
+    fn build(self) -> bool {
+        self != Steps::Upload
+    }
+
+    fn upload(self) -> bool {
+        self != Steps::Build
+    }
+}
 
 fn main() {
-    let matches = Command::new("deploy-tool")
-        .version("1.3")
-        .author("士钰 <zhoushiyu92@gmail.com>")
-        .about("一键部署Java和Vue项目,支持多环境部署,支持多模块部署")
-        .arg(
-            Arg::new("env")
-                .short('e')
-                .long("env")
-                .value_name("ENVIRONMENT")
-                .help("部署后端服务环境，多个环境用逗号分隔 (例如: dev,prod)")
-                .value_delimiter(',')
-                .required(false),
-        )
-        .arg(
-            Arg::new("vue")
-                .short('v')
-                .long("vue")
-                .value_name("ENVIRONMENT")
-                .help("部署web端环境，多个环境用逗号分隔 (例如: dev,prod)")
-                .value_delimiter(',')
-                .required(false),
-        ).arg(
-            Arg::new("model")
-                .short('m')
-                .long("model")
-                .value_name("MODEL")
-                .help("部署jar模块，多个模块用逗号分隔 (例如: admin,client,websocket)")
-                .value_delimiter(',')
-                .required(false),
-        )
-        .arg(
-            Arg::new("init-config")
-                .long("init-config")
-                .help("创建示例配置文件")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("project-dir")
-                .short('p')
-                .long("project-dir")
-                .value_name("PROJECT_DIR")
-                .help("指定项目根目录路径")
-                .required(false)
-                .default_value("."),
-        )
-        .get_matches();
+    let matches = cli::build_cli().get_matches();
+    let project_dir = matches
+        .get_one::<String>("project-dir")
+        .unwrap_or(&".".to_string())
+        .to_string();
+    let explicit_config = matches.get_one::<String>("config").map(|s| s.as_str());
+    let config_path = find_config_path(explicit_config, &project_dir);
+
+    let result = match matches.subcommand() {
+        Some(("deploy", sub_matches)) => {
+            run_deploy(&project_dir, &config_path, sub_matches, Steps::BuildAndUpload);
+            Ok(())
+        }
+        Some(("build", sub_matches)) => {
+            run_deploy(&project_dir, &config_path, sub_matches, Steps::Build);
+            Ok(())
+        }
+        Some(("upload", sub_matches)) => {
+            run_deploy(&project_dir, &config_path, sub_matches, Steps::Upload);
+            Ok(())
+        }
+        Some(("status", sub_matches)) => show_status(
+            &config_path,
+            &get_values(sub_matches, "env"),
+            &get_values(sub_matches, "model"),
+            sub_matches.get_flag("json"),
+        ),
+        Some(("logs", sub_matches)) => {
+            let options = LogOptions {
+                lines: *sub_matches.get_one::<u32>("lines").unwrap_or(&200),
+                follow: sub_matches.get_flag("follow"),
+                grep: sub_matches.get_one::<String>("grep").cloned(),
+            };
+            tail_logs(
+                &config_path,
+                &get_values(sub_matches, "env"),
+                &get_values(sub_matches, "model"),
+                &options,
+            )
+        }
+        Some(("init", sub_matches)) => init_config(explicit_config, &project_dir, sub_matches),
+        Some(("config", sub_matches)) => {
+            let result = run_config_command(&config_path, sub_matches);
+            // 校验和检查未通过时以非0状态退出,便于在CI中使用
+            if let (Err(e), Some(("validate" | "doctor", _))) = (&result, sub_matches.subcommand()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            result
+        }
+        Some(("secret", sub_matches)) => run_secret_command(&config_path, sub_matches),
+        Some((name, sub_matches)) => match ServiceAction::from_name(name) {
+            Some(action) => {
+                let environments = get_values(sub_matches, "env");
+                validate_config(&config_path, &environments, &[]).map(|_| {
+                    measure_execution_time(|| {
+                        if let Err(e) = control_services(
+                            &config_path,
+                            &environments,
+                            &get_values(sub_matches, "model"),
+                            action,
+                        ) {
+                            eprintln!("{}", e);
+                        }
+                    });
+                })
+            }
+            None => Err(format!("未知的子命令: {}", name)),
+        },
+        // 兼容旧版本的平铺参数
+        None => {
+            if matches.get_flag("init-config") {
+                init_config(explicit_config, &project_dir, &matches)
+            } else {
+                run_deploy(&project_dir, &config_path, &matches, Steps::BuildAndUpload);
+                Ok(())
+            }
+        }
+    };
 
-    // 调用方法并测量执行时间
-    measure_execution_time(|| {
-        println!("开始执行脚本程序");
-        let config_path = "./deploy.toml".to_string();
-
-        // 如果指定了init-config参数，创建示例配置文件并退出
-        if matches.get_flag("init-config") {
-            match DeployConfig::create_springboot_config(&config_path) {
-                Ok(_) => {
-                    println!("示例配置文件已创建: {}", &config_path);
-                    println!("请修改配置文件中的参数后再运行部署。");
-                    return;
-                }
-                Err(e) => {
-                    eprintln!("创建配置文件失败: {}", e);
-                    return;
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+/// 检查项目并交互式创建配置文件,--format未指定时按--config的扩展名确定格式
+fn init_config(explicit_config: Option<&str>, project_dir: &str, matches: &ArgMatches) -> Result<(), String> {
+    let format = matches
+        .get_one::<String>("format")
+        .and_then(|name| ConfigFormat::from_name(name))
+        .or_else(|| explicit_config.map(ConfigFormat::from_path))
+        .unwrap_or(ConfigFormat::Toml);
+    let config_path = init_config_path(explicit_config, project_dir, format);
+    run_init(project_dir, &config_path, format, matches.get_flag("force"))
+}
+
+/// 执行config子命令
+fn run_config_command(config_path: &str, matches: &ArgMatches) -> Result<(), String> {
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for env in DeployConfig::environment_names(config_path)? {
+                println!("{}", env);
+            }
+            Ok(())
+        }
+        Some(("schema", _)) => {
+            println!("{}", Environments::json_schema()?);
+            Ok(())
+        }
+        Some(("validate", sub_matches)) => {
+            let environments = get_values(sub_matches, "env");
+            let (vue_environments, _) = parse_vue_targets(&get_values(sub_matches, "vue"));
+            validate_config(config_path, &environments, &vue_environments)?;
+            // 按部署时的方式加载每个环境,检查环境变量和加密密码
+            let names = if environments.is_empty() && vue_environments.is_empty() {
+                DeployConfig::environment_names(config_path)?
+            } else {
+                environments.into_iter().chain(vue_environments).collect()
+            };
+            for env in &names {
+                DeployConfig::from_file(config_path, env)
+                    .map_err(|e| format!("加载{}环境配置失败: {}", env, e))?;
+            }
+            println!("配置文件校验通过: {} ({}个环境)", config_path, names.len());
+            Ok(())
+        }
+        Some(("doctor", sub_matches)) => {
+            let environments = get_values(sub_matches, "env");
+            let (vue_environments, _) = parse_vue_targets(&get_values(sub_matches, "vue"));
+            validate_config(config_path, &environments, &vue_environments)?;
+            run_doctor(config_path, &environments, &vue_environments)
+        }
+        Some(("show", sub_matches)) => {
+            for env in get_values(sub_matches, "env") {
+                // 输出合并了defaults和extends之后的最终配置
+                let mut config = DeployConfig::from_file(config_path, &env)?;
+                if !sub_matches.get_flag("show-secrets") {
+                    config.mask_secrets();
                 }
+                println!("{}", config.to_text(&env, ConfigFormat::from_path(config_path))?);
             }
+            Ok(())
         }
+        _ => Err("请指定config子命令".to_string()),
+    }
+}
 
-        let project_dir = matches
-            .get_one::<String>("project-dir")
-            .unwrap_or(&".".to_string())
-            .to_string();
-        let environments: Vec<String> = matches
-            .get_many::<String>("env")
-            .unwrap_or_default()
-            .map(|s| s.to_string())
-            .collect();
-
-        let vue_environments: Vec<String> = matches
-            .get_many::<String>("vue")
-            .unwrap_or_default()
-            .map(|s| s.to_string())
-            .collect();
-
-        let models: Vec<String> = matches
-            .get_many::<String>("model")
-            .unwrap_or_default()
-            .map(|s| s.to_string())
-            .collect();
+/// 执行secret子命令
+fn run_secret_command(config_path: &str, matches: &ArgMatches) -> Result<(), String> {
+    let (name, sub_matches) = matches.subcommand().ok_or("请指定secret子命令")?;
+    let key_file = sub_matches.get_one::<String>("key-file").map(|s| s.as_str());
+
+    if sub_matches.try_get_one::<bool>("value").ok().flatten() == Some(&true) {
+        println!("{}", encrypt_single_value(key_file)?);
+        return Ok(());
+    }
+
+    convert_config_secrets(
+        config_path,
+        &get_values(sub_matches, "env"),
+        key_file,
+        name == "encrypt",
+    )
+}
+
+/// 按命令行参数执行编译、上传或部署
+fn run_deploy(project_dir: &str, config_path: &str, matches: &ArgMatches, steps: Steps) {
+    // 调用方法并测量执行时间
+    measure_execution_time(|| {
+        println!("开始执行脚本程序");
+
+        let environments = get_values(matches, "env");
+        let models = get_values(matches, "model");
+        let (vue_environments, vue_apps) = parse_vue_targets(&get_values(matches, "vue"));
 
         println!("1.项目根目录: {}", project_dir);
         println!("2.后端环境: {:?}", environments);
         println!("3.web端环境: {:?}", vue_environments);
         println!("4.部署模块: {:?}", models);
 
+        // 编译前先校验配置,避免编译完成后才发现配置错误
+        if !environments.is_empty() || !vue_environments.is_empty() {
+            if let Err(e) = validate_config(config_path, &environments, &vue_environments) {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+
+        // --skip-build: 不编译,直接部署已有的编译产物
+        let skip_build = matches
+            .try_get_one::<bool>("skip-build")
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(false);
+        let steps = if skip_build { Steps::Upload } else { steps };
+        let artifacts = match parse_artifacts(&get_values(matches, "artifact")) {
+            Ok(artifacts) => artifacts,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+
+        if steps == Steps::Build && vue_environments.is_empty() {
+            println!("5.开始编译Java项目,请稍等...");
+            let result = build_java_only(project_dir, config_path, &environments, &models);
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+
         // 根据命令行参数选择执行部署函数
         if !environments.is_empty() {
-            println!("5.开始编译Java项目,请稍等...");
+            println!("5.开始部署Java项目,请稍等...");
             // 部署Java项目
-            if let Err(e) = deploy_java_project(&project_dir, &config_path, &environments, &models) {
+            if let Err(e) =
+                deploy_java_project(project_dir, config_path, &environments, &models, &artifacts, steps)
+            {
                 eprintln!("{}", e);
             }
         }
 
         if !vue_environments.is_empty() {
-            println!("5.开始编译Vue项目,比较慢,请稍等...");
+            println!("5.开始部署Vue项目,比较慢,请稍等...");
             // 部署Vue项目
-            if let Err(e) = deploy_vue_project(&project_dir, &config_path, &vue_environments) {
+            if let Err(e) = deploy_vue_project(project_dir, config_path, &vue_environments, &vue_apps, steps) {
                 eprintln!("{}", e);
             }
         }
     });
 }
 
+/// 解析-v参数,`环境:应用` 只部署环境中的指定应用,返回环境列表和各环境指定的应用
+///
+/// 同一环境同时以不带应用的形式出现时部署该环境的所有应用,例如 `prod,prod:admin`。
+fn parse_vue_targets(values: &[String]) -> (Vec<String>, HashMap<String, Vec<String>>) {
+    let mut environments: Vec<String> = vec![];
+    let mut apps: HashMap<String, Vec<String>> = HashMap::new();
+    let mut all_apps: Vec<String> = vec![];
+    for value in values {
+        let (env, app) = match value.split_once(':') {
+            Some((env, app)) => (env.trim(), Some(app.trim())),
+            None => (value.trim(), None),
+        };
+        if !environments.iter().any(|name| name == env) {
+            environments.push(env.to_string());
+        }
+        match app {
+            Some(app) => apps.entry(env.to_string()).or_default().push(app.to_string()),
+            None => all_apps.push(env.to_string()),
+        }
+    }
+    apps.retain(|env, _| !all_apps.contains(env));
+    (environments, apps)
+}
+
+/// 获取逗号分隔的多值参数,子命令未定义该参数时返回空列表
+fn get_values(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .try_get_many::<String>(id)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .map(|s| s.to_string())
+        .collect()
+}
+
 /// 部署Java项目的函数
 fn deploy_java_project(
     project_dir: &str,
     config_path: &str,
     environments: &[String],
     models: &[String],
+    artifacts: &HashMap<String, String>,
+    steps: Steps,
 ) -> Result<(), String> {
-
-    // 构建Java项目
-    if let Err(e) = build_java_project(project_dir) {
-        return Err(e);
+    // 通过--artifact指定了编译产物时不再编译,未指定-m时只部署这些模块
+    check_artifacts(config_path, environments, models, artifacts)?;
+    let steps = if artifacts.is_empty() { steps } else { Steps::Upload };
+    let models: Vec<String> = if models.is_empty() {
+        artifacts.keys().cloned().collect()
+    } else {
+        models.to_vec()
+    };
+    let models = models.as_slice();
+
+    // 所有环境共用一次构建,构建工具决定编译产物的默认路径
+    let options = BuildOptions::load(config_path, environments)?;
+    let build_tool = options.build_tool.resolve(project_dir);
+
+    // 构建Java项目,指定了模块时只构建这些模块
+    if steps.build() {
+        let source_dirs = selected_source_dirs(config_path, environments, models)?;
+        build_java_project(project_dir, &options, &source_dirs)?;
     }
 
     // 为每个环境创建部署任务
@@ -158,54 +371,34 @@ fn deploy_java_project(
             }
         };
 
-        // 处理jar_files字段，根据类型确定是单模块还是多模块
-        match &config.jar_files {
-            Value::Array(jar_array) => {
-                // 多模块项目情况
-                for jar_value in jar_array {
-                    if let Value::String(jar_name) = jar_value {
-                        // 应用命令行参数覆盖
-                        let config = config.clone();
-
-                        if !models.is_empty() && !models.contains(&jar_name.split('.').next().expect("配置文件中jar_name格式错误,无法匹配模块名称").to_string()) {
-                            println!("{}模块不参与部署", jar_name);
-                            continue;
-                        }
+        let modules = match config.modules() {
+            Ok(modules) => modules,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
 
-                        let project_dir = project_dir.to_string();
-                        let env = env.clone();
-                        // 多模块项目，获取编译产物路径
-                        let jar_path = format!(
-                            "{}/{}/target/{}",
-                            project_dir,
-                            jar_name.split('.').next().unwrap(),
-                            jar_name
-                        );
-
-                        spawn_deploy_thread(jar_name, jar_path, config, env, &mut handles);
-                    }
-                }
-            },
-            Value::String(jar_name) => {
-                // 单模块项目情况
-                let config = config.clone();
+        for module in modules {
+            if !models.is_empty() && !models.contains(&module.name) {
+                println!("{}模块不参与部署", module.name);
+                continue;
+            }
 
-                if !models.is_empty() && !models.contains(&jar_name.split('.').next().expect("配置文件中jar_name格式错误,无法匹配模块名称").to_string()) {
-                    println!("{}模块不参与部署", jar_name);
+            // 获取编译产物路径,优先使用--artifact指定的文件
+            let found = match artifacts.get(&module.name) {
+                Some(path) => Ok(path.clone()),
+                None => find_artifact(&project_dir, &module, build_tool),
+            };
+            let jar_path = match found {
+                Ok(jar_path) => jar_path,
+                Err(e) => {
+                    eprintln!("部署失败 {} ({}环境): {}", module.name, env, e);
                     continue;
                 }
+            };
 
-                let project_dir = project_dir.to_string();
-                let env = env.clone();
-                // 单模块项目，获取编译产物路径
-                let jar_path = format!("{}/target/{}", project_dir, jar_name);
-                
-                spawn_deploy_thread(jar_name, jar_path, config, env, &mut handles);
-            },
-            _ => {
-                eprintln!("配置文件中jar_files格式错误，必须是字符串或字符串数组");
-                continue;
-            }
+            spawn_deploy_thread(module, jar_path, config.clone(), env.clone(), &mut handles);
         }
     }
 
@@ -217,35 +410,109 @@ fn deploy_java_project(
     Ok(())
 }
 
+/// 解析--artifact <module>=<path>参数
+fn parse_artifacts(values: &[String]) -> Result<HashMap<String, String>, String> {
+    let mut artifacts = HashMap::new();
+    for value in values {
+        let (module, path) = value
+            .split_once('=')
+            .filter(|(module, path)| !module.is_empty() && !path.is_empty())
+            .ok_or_else(|| format!("--artifact格式错误,应为<module>=<path>: {}", value))?;
+        if artifacts.insert(module.to_string(), path.to_string()).is_some() {
+            return Err(format!("--artifact重复指定了{}模块", module));
+        }
+    }
+    Ok(artifacts)
+}
+
+/// 检查--artifact指定的文件存在,且模块在每个环境中都已配置
+///
+/// 指定了--artifact时不再编译,-m中的模块都必须通过--artifact指定编译产物,避免部署target/下的旧jar包。
+fn check_artifacts(
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+    artifacts: &HashMap<String, String>,
+) -> Result<(), String> {
+    if artifacts.is_empty() {
+        return Ok(());
+    }
+    for module in models {
+        if !artifacts.contains_key(module) {
+            return Err(format!(
+                "指定了--artifact时不会编译,{}模块未通过--artifact指定编译产物,请一并指定或单独部署",
+                module
+            ));
+        }
+    }
+    for (module, path) in artifacts {
+        if !Path::new(path).is_file() {
+            return Err(format!("{}模块指定的编译产物不存在: {}", module, path));
+        }
+    }
+    for env in environments {
+        let modules = DeployConfig::from_file(config_path, env)?.modules()?;
+        for module in artifacts.keys() {
+            if !modules.iter().any(|m| &m.name == module) {
+                return Err(format!("--artifact指定的{}模块不在{}环境的模块列表中", module, env));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 只编译Java项目: 指定了环境时使用这些环境的构建配置并按-m只构建选中的模块,否则使用[defaults]
+fn build_java_only(
+    project_dir: &str,
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+) -> Result<(), String> {
+    if environments.is_empty() && !models.is_empty() {
+        return Err("通过-m指定模块时需要同时通过-e指定环境".to_string());
+    }
+    let options = BuildOptions::load(config_path, environments)?;
+    let source_dirs = selected_source_dirs(config_path, environments, models)?;
+    build_java_project(project_dir, &options, &source_dirs)
+}
+
+/// 各环境中被选中模块的源码目录,未指定模块时为空
+fn selected_source_dirs(
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+) -> Result<Vec<String>, String> {
+    let mut source_dirs = vec![];
+    if models.is_empty() {
+        return Ok(source_dirs);
+    }
+    for env in environments {
+        for module in DeployConfig::from_file(config_path, env)?.modules()? {
+            if models.contains(&module.name) && !source_dirs.contains(&module.source_dir) {
+                source_dirs.push(module.source_dir);
+            }
+        }
+    }
+    Ok(source_dirs)
+}
+
 // 创建并运行部署线程的辅助函数
 fn spawn_deploy_thread(
-    jar_name: &str,
+    module: Module,
     jar_path: String,
     config: DeployConfig,
     env: String,
     handles: &mut Vec<thread::JoinHandle<()>>
 ) {
-    let jar_name = jar_name.to_string();
-    
     let handle = thread::spawn(move || {
-        let remote_path = format!("{}/{}", config.remote_base_path, jar_name);
-
-        println!("开始部署 {} 到 {} 环境", jar_name, env);
+        println!("开始部署 {} 到 {} 环境", module.name, env);
 
         // 上传并运行 JAR 包
-        if let Err(e) = upload_and_run_jar(
-            &config.server,
-            &config.username,
-            &config.password,
-            &jar_path,
-            &remote_path,
-            &config.java_path,
-            &env,
-        ) {
-            eprintln!("部署失败 {} ({}环境): {}", jar_name, env, e);
+        if let Err(e) = upload_and_run_jar(&config, &module, &jar_path, &env) {
+            eprintln!("部署失败 {} ({}环境): {}", module.name, env, e);
             return;
         }
-        println!("部署成功: {} ({}环境)", jar_name, env);
+        println!("部署成功: {} ({}环境)", module.name, env);
     });
     handles.push(handle);
 }
@@ -275,56 +542,305 @@ fn deploy_vue_project(
     project_dir: &str,
     config_path: &str,
     environments: &[String],
+    apps: &HashMap<String, Vec<String>>,
+    steps: Steps,
 ) -> Result<(), String> {
-    // 为每个环境创建部署任务
-    let mut handles = vec![];
-
+    let mut targets = vec![];
     for env in environments {
-        let env = env.to_string();
-        let config_path = config_path.to_string();
-        let project_dir = project_dir.to_string();
-
-        let config = match DeployConfig::from_file(&config_path, &env) {
+        let config = match DeployConfig::from_file(config_path, env) {
             Ok(config) => config,
             Err(e) => {
                 eprintln!("加载{}环境配置失败: {}", env, e);
                 continue;
             }
         };
+        let web_apps = config.web_apps();
+        // -v 环境:应用 指定了应用时只部署这些应用
+        let selected = apps.get(env).map(Vec::as_slice).unwrap_or_default();
+        for name in selected {
+            if !web_apps.iter().any(|app| &app.name == name) {
+                let names: Vec<&str> = web_apps.iter().map(|app| app.name.as_str()).collect();
+                return Err(format!("{}环境中没有web应用: {}，可选: {}", env, name, names.join(",")));
+            }
+        }
+        let multiple = !config.web_apps.is_empty();
+        for app in web_apps {
+            if !selected.is_empty() && !selected.contains(&app.name) {
+                continue;
+            }
+            let label = if multiple {
+                format!("{}环境{}应用", env, app.name)
+            } else {
+                format!("{}环境", env)
+            };
+            targets.push(VueTarget {
+                label,
+                app,
+                config: config.clone(),
+            });
+        }
+    }
+
+    // 依赖安装在所有构建开始前执行一次,避免并行构建时同时写node_modules
+    if steps.build() {
+        let mut installs: Vec<(String, PackageManager)> = vec![];
+        for target in targets.iter().filter(|target| target.config.install) {
+            let web_dir = target.app.project_dir(project_dir);
+            let package_manager = target.config.package_manager.resolve(&web_dir);
+            if !installs.contains(&(web_dir.clone(), package_manager)) {
+                installs.push((web_dir, package_manager));
+            }
+        }
+        for (web_dir, package_manager) in installs {
+            install_vue_dependencies(&web_dir, package_manager)?;
+        }
+    }
 
+    // 每个web端目录一个线程,同一目录下的构建共用node_modules和打包缓存,按顺序执行,不同目录并行构建
+    let mut handles = vec![];
+    for builds in group_vue_builds(project_dir, targets) {
         let handle = thread::spawn(move || {
-            // 构建Vue项目
-            build_vue_project(&project_dir, &config.scripts).expect("构建Vue项目失败");
-
-            // 压缩产出目录文件zip
-            let output_dir = format!("{}/{}", project_dir, config.output_dir);
-            let zip_path = format!("{}/{}.zip", project_dir, config.output_dir);
-            let zip_file = File::create(&zip_path).expect("创建新的zip文件失败");
-            let mut zip = ZipWriter::new(zip_file);
-            let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
-            zip_dir(&mut zip, &output_dir, options).expect("压缩失败");
-            zip.finish().expect("完成ZIP文件失败");
-
-            // 上传zip文件
-            let remote_path = format!("{}/{}", config.remote_base_path, config.output_dir);
-
-            if let Err(e) = upload_file(
-                &config.server,
-                &config.username,
-                &config.password,
-                &zip_path,
-                &remote_path,
-            ) {
-                eprintln!("上传失败 {} ({}环境): {}", config.output_dir, env, e);
-                return;
+            let output_dirs: Vec<String> = builds.iter().map(|build| build.output_dir.clone()).collect();
+            let mut uploads = vec![];
+            for build in builds {
+                let shared_dir = output_dirs.iter().filter(|dir| **dir == build.output_dir).count() > 1;
+                let labels = build.labels();
+                if steps.build() {
+                    if let Err(e) = build_vue_project(&build.web_dir, build.package_manager, &build.scripts) {
+                        eprintln!("构建Vue项目失败 ({}): {}", labels, e);
+                        continue;
+                    }
+                }
+
+                // 同一输出目录有多个构建时,压缩包按脚本名区分,避免被下一次构建覆盖
+                let output_dir = format!("{}/{}", build.web_dir, build.output_dir);
+                let zip_path = if shared_dir {
+                    format!("{}/{}-{}.zip", build.web_dir, build.output_dir, file_name_part(&build.scripts))
+                } else {
+                    format!("{}/{}.zip", build.web_dir, build.output_dir)
+                };
+                if !steps.build() && !Path::new(&output_dir).is_dir() {
+                    eprintln!("编译产物目录不存在: {} ({})，请先编译", output_dir, labels);
+                    continue;
+                }
+                if let Err(e) = zip_output_dir(&output_dir, &zip_path) {
+                    eprintln!("打包失败: {} ({}): {}", zip_path, labels, e);
+                    continue;
+                }
+
+                if !steps.upload() {
+                    println!("打包成功: {} ({})", zip_path, labels);
+                    continue;
+                }
+
+                // 上传zip文件,所有环境和应用并行上传
+                for target in build.targets {
+                    let zip_path = zip_path.clone();
+                    uploads.push(thread::spawn(move || {
+                        let remote_path = &target.app.remote_path;
+                        if let Err(e) = upload_file(&target.config, &zip_path, remote_path) {
+                            eprintln!("上传失败 {} ({}): {}", remote_path, target.label, e);
+                            return;
+                        }
+                        println!("上传成功: {} -> {} ({})", target.app.output_dir, remote_path, target.label);
+                    }));
+                }
+            }
+            for upload in uploads {
+                if upload.join().is_err() {
+                    eprintln!("web端上传线程异常退出");
+                }
             }
-            println!("上传成功: {} ({}环境)", config.output_dir, env);
         });
         handles.push(handle);
     }
     // 等待所有线程完成
     for handle in handles {
-        handle.join().unwrap();
+        if handle.join().is_err() {
+            eprintln!("web端构建线程异常退出");
+        }
     }
     Ok(())
 }
+
+/// 部署到一个环境的web端应用
+struct VueTarget {
+    /// 输出信息中的名称,例如"prod环境"或"prod环境admin应用"
+    label: String,
+    app: WebApp,
+    config: DeployConfig,
+}
+
+/// 一次web端构建,应用目录、scripts、output_dir和包管理器都相同的应用共用构建结果
+struct VueBuild {
+    web_dir: String,
+    package_manager: PackageManager,
+    scripts: String,
+    output_dir: String,
+    targets: Vec<VueTarget>,
+}
+
+impl VueBuild {
+    fn labels(&self) -> String {
+        self.targets
+            .iter()
+            .map(|target| target.label.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// 按应用目录分组,组内output_dir和scripts都相同的应用合并为一次构建,保持原有顺序
+fn group_vue_builds(project_dir: &str, targets: Vec<VueTarget>) -> Vec<Vec<VueBuild>> {
+    let mut groups: Vec<Vec<VueBuild>> = vec![];
+    for target in targets {
+        let web_dir = target.app.project_dir(project_dir);
+        let package_manager = target.config.package_manager.resolve(&web_dir);
+        let group = match groups
+            .iter_mut()
+            .position(|group| group[0].web_dir == web_dir)
+        {
+            Some(index) => &mut groups[index],
+            None => {
+                groups.push(vec![]);
+                groups.last_mut().unwrap()
+            }
+        };
+        match group
+            .iter_mut()
+            .find(|build| {
+                build.output_dir == target.app.output_dir
+                    && build.scripts == target.app.scripts
+                    && build.package_manager == package_manager
+            })
+        {
+            Some(build) => build.targets.push(target),
+            None => group.push(VueBuild {
+                web_dir,
+                package_manager,
+                scripts: target.app.scripts.clone(),
+                output_dir: target.app.output_dir.clone(),
+                targets: vec![target],
+            }),
+        }
+    }
+    groups
+}
+
+/// 压缩编译产物目录
+fn zip_output_dir(output_dir: &str, zip_path: &str) -> Result<(), String> {
+    let zip_file = File::create(zip_path).map_err(|e| format!("创建zip文件失败: {}", e))?;
+    let mut zip = ZipWriter::new(zip_file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip_dir(&mut zip, output_dir, options)?;
+    zip.finish().map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+    Ok(())
+}
+
+/// 将脚本名转换为可用于文件名的形式,如build:prod转换为build-prod
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_artifacts_reads_module_paths() {
+        let artifacts =
+            parse_artifacts(&strings(&["admin=build/admin.jar", "client=a=b.jar"])).unwrap();
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(artifacts["admin"], "build/admin.jar");
+        // 只按第一个=拆分
+        assert_eq!(artifacts["client"], "a=b.jar");
+    }
+
+    #[test]
+    fn parse_artifacts_rejects_bad_values() {
+        assert!(parse_artifacts(&strings(&["admin"])).is_err());
+        assert!(parse_artifacts(&strings(&["=admin.jar"])).is_err());
+        assert!(parse_artifacts(&strings(&["admin="])).is_err());
+        assert!(parse_artifacts(&strings(&["admin=a.jar", "admin=b.jar"]))
+            .unwrap_err()
+            .contains("重复"));
+    }
+
+    #[test]
+    fn check_artifacts_requires_artifact_for_every_selected_module() {
+        let artifacts = HashMap::from([("admin".to_string(), "admin.jar".to_string())]);
+        let error = check_artifacts(
+            "deploy.toml",
+            &[],
+            &strings(&["admin", "client"]),
+            &artifacts,
+        )
+        .unwrap_err();
+        assert!(error.contains("client"), "{}", error);
+        assert!(check_artifacts("deploy.toml", &[], &strings(&["admin"]), &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn parse_vue_targets_collects_app_filters() {
+        let (environments, apps) = parse_vue_targets(&strings(&["dev", "prod:admin", "prod:h5"]));
+        assert_eq!(environments, strings(&["dev", "prod"]));
+        assert_eq!(apps.get("prod"), Some(&strings(&["admin", "h5"])));
+        assert!(!apps.contains_key("dev"));
+    }
+
+    #[test]
+    fn parse_vue_targets_plain_env_selects_all_apps() {
+        let (environments, apps) = parse_vue_targets(&strings(&["prod", "prod:admin"]));
+        assert_eq!(environments, strings(&["prod"]));
+        assert!(apps.is_empty());
+        let (_, apps) = parse_vue_targets(&strings(&["prod:admin", "prod"]));
+        assert!(apps.is_empty());
+    }
+
+    fn target(env: &str, content: &str) -> Vec<VueTarget> {
+        let config: DeployConfig = toml::from_str(&format!(
+            "server = \"h:22\"\nusername = \"u\"\njava_path = \"/j\"\nremote_base_path = \"/opt\"\n{}",
+            content
+        ))
+        .unwrap();
+        config
+            .web_apps()
+            .into_iter()
+            .map(|app| VueTarget {
+                label: format!("{}:{}", env, app.name),
+                app,
+                config: config.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn group_vue_builds_shares_identical_builds_per_web_dir() {
+        let mut targets = target("dev", "scripts = \"build\"\noutput_dir = \"dist\"");
+        targets.extend(target("test", "scripts = \"build\"\noutput_dir = \"dist\""));
+        targets.extend(target(
+            "prod",
+            "scripts = \"build:prod\"\noutput_dir = \"dist\"",
+        ));
+        targets.extend(target(
+            "prod",
+            "[[web_apps]]\nname = \"h5\"\nweb_dir = \"h5\"\nscripts = \"build\"\noutput_dir = \"dist\"",
+        ));
+
+        let groups = group_vue_builds("/project", targets);
+        assert_eq!(groups.len(), 2);
+        // 同一目录下的构建按顺序执行,相同scripts和output_dir的环境共用一次构建
+        assert_eq!(groups[0].len(), 2);
+        assert_eq!(groups[0][0].web_dir, "/project");
+        assert_eq!(groups[0][0].labels(), "dev:dist, test:dist");
+        assert_eq!(groups[0][1].scripts, "build:prod");
+        assert_eq!(groups[1][0].web_dir, "/project/h5");
+        assert_eq!(groups[1][0].labels(), "prod:h5");
+    }
+}
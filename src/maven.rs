@@ -0,0 +1,165 @@
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 从pom.xml中读取的模块信息
+pub struct Pom {
+    pub artifact_id: String,
+    /// 打包方式,未配置时为jar
+    pub packaging: String,
+    /// 聚合模块的<modules>
+    pub modules: Vec<String>,
+    /// 解析了属性引用的build.finalName,未配置或含有无法解析的属性时为空
+    pub final_name: Option<String>,
+    /// 是否使用了spring-boot-maven-plugin
+    pub spring_boot: bool,
+}
+
+/// 读取目录下的pom.xml,文件不存在时返回None
+pub fn read_pom(dir: &Path) -> Result<Option<Pom>, String> {
+    let pom_path = dir.join("pom.xml");
+    let content = match fs::read_to_string(&pom_path) {
+        Ok(content) => content,
+        Err(_) => return Ok(None),
+    };
+    let doc = Document::parse(&content)
+        .map_err(|e| format!("解析{}失败: {}", pom_path.display(), e))?;
+    let project = doc.root_element();
+
+    let artifact_id = child_text(project, "artifactId").unwrap_or_default().to_string();
+    let modules = child(project, "modules")
+        .map(|modules| {
+            modules
+                .children()
+                .filter(|node| node.has_tag_name("module"))
+                .filter_map(|node| node.text().map(|text| text.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let final_name = child(project, "build")
+        .and_then(|build| child_text(build, "finalName"))
+        .map(|name| resolve_properties(name, project))
+        // 含有无法解析的属性引用时无法确定jar包名
+        .filter(|name| !name.contains("${"));
+
+    Ok(Some(Pom {
+        artifact_id,
+        packaging: child_text(project, "packaging").unwrap_or("jar").to_string(),
+        modules,
+        final_name,
+        spring_boot: content.contains("spring-boot-maven-plugin"),
+    }))
+}
+
+/// 替换${project.artifactId}、${project.version}和<properties>中定义的属性,版本号未配置时使用<parent>的版本
+fn resolve_properties(text: &str, project: Node) -> String {
+    let mut properties: HashMap<String, String> = HashMap::new();
+    if let Some(node) = child(project, "properties") {
+        for property in node.children().filter(|node| node.is_element()) {
+            if let Some(value) = property.text() {
+                properties.insert(property.tag_name().name().to_string(), value.trim().to_string());
+            }
+        }
+    }
+    let parent = child(project, "parent");
+    let coordinate = |name: &str| {
+        child_text(project, name).or_else(|| parent.and_then(|parent| child_text(parent, name)))
+    };
+    for name in ["artifactId", "groupId", "version"] {
+        if let Some(value) = coordinate(name) {
+            properties.insert(format!("project.{}", name), value.to_string());
+            properties.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    let mut result = text.to_string();
+    for (name, value) in &properties {
+        result = result.replace(&format!("${{{}}}", name), value);
+    }
+    result
+}
+
+/// 查找直接子元素
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// 直接子元素的文本
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|child| child.text()).map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn resolve(text: &str, pom: &str) -> String {
+        let doc = Document::parse(pom).unwrap();
+        resolve_properties(text, doc.root_element())
+    }
+
+    #[test]
+    fn resolves_project_coordinates_and_properties() {
+        let pom = "<project>\
+            <parent><groupId>com.example</groupId><version>2.1.0</version></parent>\
+            <artifactId>admin</artifactId>\
+            <properties><app.name>console</app.name></properties>\
+            </project>";
+        assert_eq!(
+            resolve("${project.artifactId}-${project.version}", pom),
+            "admin-2.1.0"
+        );
+        assert_eq!(resolve("${artifactId}-${app.name}", pom), "admin-console");
+        assert_eq!(resolve("${project.groupId}", pom), "com.example");
+        // 无法解析的属性保持原样
+        assert_eq!(resolve("${missing}-app", pom), "${missing}-app");
+
+        // 模块自己的版本号优先于<parent>
+        let pom = "<project><parent><version>1.0</version></parent>\
+            <artifactId>admin</artifactId><version>3.0</version></project>";
+        assert_eq!(resolve("${project.version}", pom), "3.0");
+    }
+
+    #[test]
+    fn read_pom_uses_final_name_only_when_resolvable() {
+        let dir = env::temp_dir().join(format!("deploy-tool-pom-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(read_pom(&dir).unwrap().is_none());
+
+        fs::write(
+            dir.join("pom.xml"),
+            "<project><artifactId>admin</artifactId>\
+             <build><finalName>${project.artifactId}-app</finalName></build></project>",
+        )
+        .unwrap();
+        let pom = read_pom(&dir).unwrap().unwrap();
+        assert_eq!(pom.artifact_id, "admin");
+        assert_eq!(pom.packaging, "jar");
+        assert_eq!(pom.final_name.as_deref(), Some("admin-app"));
+
+        fs::write(
+            dir.join("pom.xml"),
+            "<project><artifactId>admin</artifactId>\
+             <build><finalName>${unknown}</finalName></build></project>",
+        )
+        .unwrap();
+        assert!(read_pom(&dir).unwrap().unwrap().final_name.is_none());
+
+        fs::write(
+            dir.join("pom.xml"),
+            "<project><artifactId>parent</artifactId><packaging>pom</packaging>\
+             <modules><module>admin</module><module> client </module></modules></project>",
+        )
+        .unwrap();
+        let pom = read_pom(&dir).unwrap().unwrap();
+        assert_eq!(pom.packaging, "pom");
+        assert_eq!(pom.modules, ["admin", "client"]);
+        assert!(pom.final_name.is_none());
+
+        fs::write(dir.join("pom.xml"), "<project>").unwrap();
+        assert!(read_pom(&dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,433 @@
+use crate::config::{user_config_dir, ConfigFormat};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+use sha2::Sha256;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use toml_edit::{DocumentMut, TableLike};
+
+/// 加密值的前缀
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+/// 需要加密的配置字段
+pub const SECRET_FIELDS: [&str; 2] = ["password", "sudo_password"];
+/// 口令环境变量,设置后优先于密钥文件
+const PASSPHRASE_ENV: &str = "DEPLOY_TOOL_PASSPHRASE";
+/// 密钥文件路径环境变量
+const KEY_FILE_ENV: &str = "DEPLOY_TOOL_KEY_FILE";
+/// 默认密钥文件名,位于用户级配置目录下
+const DEFAULT_KEY_FILE: &str = "secret.key";
+/// 密钥派生迭代次数
+const PBKDF2_ROUNDS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// 读取加密密钥: --key-file > $DEPLOY_TOOL_PASSPHRASE > $DEPLOY_TOOL_KEY_FILE > 默认密钥文件
+pub fn load_key(key_file: Option<&str>) -> Result<Vec<u8>, String> {
+    if let Some(path) = key_file {
+        return read_key_file(&PathBuf::from(path));
+    }
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase.into_bytes());
+    }
+    if let Ok(path) = env::var(KEY_FILE_ENV) {
+        return read_key_file(&PathBuf::from(path));
+    }
+    match default_key_file() {
+        Some(path) if path.is_file() => read_key_file(&path),
+        _ => Err(format!(
+            "未找到加密密钥，请通过--key-file、{}或{}指定",
+            PASSPHRASE_ENV, KEY_FILE_ENV
+        )),
+    }
+}
+
+/// 读取加密密钥,没有任何密钥时在默认位置生成新的密钥文件
+fn load_or_create_key(key_file: Option<&str>) -> Result<Vec<u8>, String> {
+    if key_file.is_some()
+        || env::var_os(PASSPHRASE_ENV).is_some()
+        || env::var_os(KEY_FILE_ENV).is_some()
+    {
+        return load_key(key_file);
+    }
+
+    let path = default_key_file().ok_or("无法确定默认密钥文件路径")?;
+    if path.is_file() {
+        return read_key_file(&path);
+    }
+
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建密钥目录失败: {}", e))?;
+    }
+    fs::write(&path, STANDARD.encode(key)).map_err(|e| format!("写入密钥文件失败: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("设置密钥文件权限失败: {}", e))?;
+    }
+    println!("已生成新的密钥文件: {}，请妥善保管并分发给需要部署的成员", path.display());
+    read_key_file(&path)
+}
+
+fn default_key_file() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(DEFAULT_KEY_FILE))
+}
+
+fn read_key_file(path: &PathBuf) -> Result<Vec<u8>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("无法读取密钥文件 {}: {}", path.display(), e))?;
+    Ok(content.trim().as_bytes().to_vec())
+}
+
+/// 由密钥和盐派生AES-256密钥
+fn derive_key(key: &[u8], salt: &[u8]) -> Aes256Gcm {
+    let mut derived = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(key, salt, PBKDF2_ROUNDS, &mut derived);
+    Aes256Gcm::new(&derived.into())
+}
+
+/// 加密明文,返回enc:前缀的base64字符串(盐+随机数+密文)
+pub fn encrypt_value(plain: &str, key: &[u8]) -> Result<String, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = derive_key(key, &salt)
+        .encrypt(&nonce, plain.as_bytes())
+        .map_err(|e| format!("加密失败: {}", e))?;
+
+    let mut data = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(data)))
+}
+
+/// 解密enc:前缀的加密值
+pub fn decrypt_value(encrypted: &str, key: &[u8]) -> Result<String, String> {
+    let encoded = encrypted
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| format!("加密值必须以{}开头", ENCRYPTED_PREFIX))?;
+    let data = STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("加密值格式错误: {}", e))?;
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err("加密值格式错误: 长度不足".to_string());
+    }
+
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let plain = derive_key(key, salt)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "解密失败，密钥不正确或加密值已损坏".to_string())?;
+    String::from_utf8(plain).map_err(|e| format!("解密结果不是有效的UTF-8: {}", e))
+}
+
+/// 解密环境配置中enc:开头的密码字段,其他字段保持原样,没有加密值时不读取密钥
+pub fn decrypt_secrets(mut value: Value) -> Result<Value, String> {
+    let mut key = None;
+    if let Value::Object(map) = &mut value {
+        for field in SECRET_FIELDS {
+            let text = match map.get_mut(field) {
+                Some(Value::String(text)) if text.starts_with(ENCRYPTED_PREFIX) => text,
+                _ => continue,
+            };
+            if key.is_none() {
+                key = Some(load_key(None)?);
+            }
+            *text = decrypt_value(text, key.as_deref().unwrap_or_default())
+                .map_err(|e| format!("{}字段: {}", field, e))?;
+        }
+    }
+    Ok(value)
+}
+
+/// 加密或解密配置文件中各环境的密码字段,TOML文件保留注释和格式
+pub fn convert_config_secrets(
+    config_path: &str,
+    environments: &[String],
+    key_file: Option<&str>,
+    encrypt: bool,
+) -> Result<(), String> {
+    let content = fs::read_to_string(config_path)
+        .map_err(|e| format!("无法读取配置文件 {}: {}", config_path, e))?;
+
+    let key = if encrypt {
+        load_or_create_key(key_file)?
+    } else {
+        load_key(key_file)?
+    };
+
+    let converter = SecretConverter {
+        environments,
+        key: &key,
+        encrypt,
+    };
+    let format = ConfigFormat::from_path(config_path);
+    let (content, changed) = match format {
+        ConfigFormat::Toml => converter.convert_toml(&content)?,
+        _ => converter.convert_structured(&content, format)?,
+    };
+
+    if changed == 0 {
+        println!("没有需要处理的字段");
+        return Ok(());
+    }
+
+    fs::write(config_path, content).map_err(|e| format!("写入配置文件失败: {}", e))?;
+    println!("配置文件已更新: {}", config_path);
+    Ok(())
+}
+
+/// 按环境转换密码字段
+struct SecretConverter<'a> {
+    /// 处理的环境,为空时处理全部环境
+    environments: &'a [String],
+    key: &'a [u8],
+    encrypt: bool,
+}
+
+impl SecretConverter<'_> {
+    fn includes(&self, env: &str) -> bool {
+        self.environments.is_empty() || self.environments.iter().any(|name| name == env)
+    }
+
+    /// 转换单个字段的值,不需要处理时返回None,scope为"prod环境"或"[defaults]"
+    fn convert(&self, scope: &str, field: &str, text: &str) -> Result<Option<String>, String> {
+        // 环境变量引用和空值不需要加密
+        let plain = !text.is_empty() && !text.starts_with(ENCRYPTED_PREFIX) && !text.contains("${");
+        let converted = if self.encrypt && plain {
+            encrypt_value(text, self.key)?
+        } else if !self.encrypt && text.starts_with(ENCRYPTED_PREFIX) {
+            decrypt_value(text, self.key).map_err(|e| format!("{}的{}字段: {}", scope, field, e))?
+        } else {
+            return Ok(None);
+        };
+        println!("{} {}的{}字段", if self.encrypt { "已加密" } else { "已解密" }, scope, field);
+        Ok(Some(converted))
+    }
+
+    /// 通过toml_edit修改,保留文件中的注释和格式
+    fn convert_toml(&self, content: &str) -> Result<(String, usize), String> {
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| format!("解析配置文件失败: {}", e))?;
+
+        // [defaults]由所有环境继承,始终处理
+        let mut changed = match doc.get_mut("defaults").and_then(|item| item.as_table_like_mut()) {
+            Some(table) => self.convert_toml_table("[defaults]", table)?,
+            None => 0,
+        };
+        let tables = doc
+            .get_mut("environments")
+            .and_then(|item| item.as_table_like_mut())
+            .ok_or("配置文件中没有environments")?;
+        for (env, table) in tables.iter_mut() {
+            if !self.includes(env.get()) {
+                continue;
+            }
+            if let Some(table) = table.as_table_like_mut() {
+                changed += self.convert_toml_table(&format!("{}环境", env.get()), table)?;
+            }
+        }
+        Ok((doc.to_string(), changed))
+    }
+
+    fn convert_toml_table(&self, scope: &str, table: &mut dyn TableLike) -> Result<usize, String> {
+        let mut changed = 0;
+        for field in SECRET_FIELDS {
+            let value = match table.get_mut(field).and_then(|item| item.as_value_mut()) {
+                Some(value) => value,
+                None => continue,
+            };
+            let converted = match value.as_str() {
+                Some(text) => self.convert(scope, field, text)?,
+                None => continue,
+            };
+            if let Some(converted) = converted {
+                let decor = value.decor().clone();
+                *value = converted.into();
+                *value.decor_mut() = decor;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// YAML和JSON保留键的顺序,YAML中的注释会丢失
+    fn convert_structured(&self, content: &str, format: ConfigFormat) -> Result<(String, usize), String> {
+        // JSON是YAML的子集,统一按YAML解析以保留键的顺序
+        let mut root: serde_norway::Value =
+            serde_norway::from_str(content).map_err(|e| format!("解析配置文件失败: {}", e))?;
+
+        let mut changed = match root.get_mut("defaults") {
+            Some(table) => self.convert_structured_table("[defaults]", table)?,
+            None => 0,
+        };
+        let tables = root
+            .get_mut("environments")
+            .and_then(|value| value.as_mapping_mut())
+            .ok_or("配置文件中没有environments")?;
+        for (env, table) in tables.iter_mut() {
+            if let Some(env) = env.as_str().filter(|env| self.includes(env)) {
+                changed += self.convert_structured_table(&format!("{}环境", env), table)?;
+            }
+        }
+
+        let content = match format {
+            ConfigFormat::Json => serde_json::to_string_pretty(&root)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+            _ => serde_norway::to_string(&root).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("序列化配置失败: {}", e))?;
+        Ok((content, changed))
+    }
+
+    fn convert_structured_table(&self, scope: &str, table: &mut serde_norway::Value) -> Result<usize, String> {
+        let mut changed = 0;
+        for field in SECRET_FIELDS {
+            let value = match table.get_mut(field) {
+                Some(value) => value,
+                None => continue,
+            };
+            let converted = match value.as_str() {
+                Some(text) => self.convert(scope, field, text)?,
+                None => continue,
+            };
+            if let Some(converted) = converted {
+                *value = serde_norway::Value::String(converted);
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// 加密单个值并输出,可以直接粘贴到配置文件中
+///
+/// 要加密的值在终端中通过不回显的提示输入,否则从标准输入读取一行,避免明文出现在shell历史和进程列表中。
+pub fn encrypt_single_value(key_file: Option<&str>) -> Result<String, String> {
+    let plain = if io::stdin().is_terminal() {
+        rpassword::prompt_password("请输入要加密的值: ").map_err(|e| format!("读取输入失败: {}", e))?
+    } else {
+        let mut line = String::new();
+        io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| format!("读取输入失败: {}", e))?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if plain.is_empty() {
+        return Err("要加密的值不能为空".to_string());
+    }
+    let key = load_or_create_key(key_file)?;
+    encrypt_value(&plain, &key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"test-passphrase";
+
+    #[test]
+    fn encrypt_decrypt_roundtrip() {
+        let encrypted = encrypt_value("p@ss word", KEY).unwrap();
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        // 每次加密使用随机的盐和随机数
+        assert_ne!(encrypted, encrypt_value("p@ss word", KEY).unwrap());
+        assert_eq!(decrypt_value(&encrypted, KEY).unwrap(), "p@ss word");
+        assert!(decrypt_value(&encrypted, b"wrong-key").is_err());
+        assert!(decrypt_value("enc:AAAA", KEY).is_err());
+        assert!(decrypt_value("plain", KEY).is_err());
+    }
+
+    #[test]
+    fn decrypt_secrets_only_touches_password_fields() {
+        env::set_var(PASSPHRASE_ENV, "secret-test-passphrase");
+        let password = encrypt_value("ssh-pass", b"secret-test-passphrase").unwrap();
+        let value = serde_json::json!({
+            "password": password,
+            "sudo_password": "",
+            "java_path": "enc:literal",
+        });
+        let value = decrypt_secrets(value).unwrap();
+        assert_eq!(value["password"], "ssh-pass");
+        assert_eq!(value["sudo_password"], "");
+        assert_eq!(value["java_path"], "enc:literal");
+    }
+
+    #[test]
+    fn convert_toml_encrypts_and_decrypts_password_fields() {
+        let content = r#"# 注释保留
+[environments.prod]
+password = "plain"
+sudo_password = ""
+username = "enc:not-a-secret"
+
+[environments.dev]
+password = "${DEV_PASS}"
+"#;
+        let encryptor = SecretConverter {
+            environments: &[],
+            key: KEY,
+            encrypt: true,
+        };
+        let (encrypted, changed) = encryptor.convert_toml(content).unwrap();
+        // 空值和环境变量引用不加密
+        assert_eq!(changed, 1);
+        assert!(encrypted.starts_with("# 注释保留"));
+        assert!(encrypted.contains("sudo_password = \"\""));
+        assert!(encrypted.contains("${DEV_PASS}"));
+        assert!(!encrypted.contains("\"plain\""));
+
+        let decryptor = SecretConverter {
+            environments: &[],
+            key: KEY,
+            encrypt: false,
+        };
+        let (decrypted, changed) = decryptor.convert_toml(&encrypted).unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(decrypted, content);
+    }
+
+    #[test]
+    fn convert_includes_defaults_table() {
+        let environments = vec!["prod".to_string()];
+        let encryptor = SecretConverter {
+            environments: &environments,
+            key: KEY,
+            encrypt: true,
+        };
+        let content = r#"[defaults]
+sudo_password = "root-pass"
+
+[environments.prod]
+password = "prod-pass"
+
+[environments.dev]
+password = "dev-pass"
+"#;
+        let (encrypted, changed) = encryptor.convert_toml(content).unwrap();
+        // [defaults]始终处理,未选中的环境不处理
+        assert_eq!(changed, 2);
+        assert!(!encrypted.contains("root-pass"));
+        assert!(encrypted.contains("\"dev-pass\""));
+
+        let content = "defaults:\n  sudo_password: root-pass\nenvironments:\n  prod:\n    password: prod-pass\n";
+        let (encrypted, changed) = encryptor
+            .convert_structured(content, ConfigFormat::Yaml)
+            .unwrap();
+        assert_eq!(changed, 2);
+        let root: serde_norway::Value = serde_norway::from_str(&encrypted).unwrap();
+        let sudo_password = root["defaults"]["sudo_password"].as_str().unwrap();
+        assert_eq!(decrypt_value(sudo_password, KEY).unwrap(), "root-pass");
+    }
+}
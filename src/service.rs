@@ -0,0 +1,89 @@
+use crate::config::DeployConfig;
+use crate::upload::{rollback_jar, run_jar, stop_jar};
+use std::thread;
+
+/// 服务控制操作
+#[derive(Clone, Copy)]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+    Rollback,
+}
+
+impl ServiceAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "start" => Some(ServiceAction::Start),
+            "stop" => Some(ServiceAction::Stop),
+            "restart" => Some(ServiceAction::Restart),
+            "rollback" => Some(ServiceAction::Rollback),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ServiceAction::Start => "启动",
+            ServiceAction::Stop => "停止",
+            ServiceAction::Restart => "重启",
+            ServiceAction::Rollback => "回滚",
+        }
+    }
+}
+
+/// 不上传新包,直接启动、停止、重启或回滚各环境下的模块
+pub fn control_services(
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+    action: ServiceAction,
+) -> Result<(), String> {
+    // 为每个环境的每个模块创建任务
+    let mut handles = vec![];
+
+    for env in environments {
+        let config = match DeployConfig::from_file(config_path, env) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("加载{}环境配置失败: {}", env, e);
+                continue;
+            }
+        };
+
+        for module in config.modules()? {
+            if !models.is_empty() && !models.contains(&module.name) {
+                println!("{}模块不参与{}", module.name, action.label());
+                continue;
+            }
+
+            let config = config.clone();
+            let env = env.to_string();
+
+            let handle = thread::spawn(move || {
+                let remote_path = module.remote_path.as_str();
+                println!("开始{} {} ({}环境)", action.label(), module.name, env);
+
+                let result = match action {
+                    ServiceAction::Start => run_jar(&config, &module, &env),
+                    ServiceAction::Stop => stop_jar(&config, remote_path, &env),
+                    ServiceAction::Restart => stop_jar(&config, remote_path, &env)
+                        .and_then(|_| run_jar(&config, &module, &env)),
+                    ServiceAction::Rollback => rollback_jar(&config, &module, &env),
+                };
+
+                match result {
+                    Ok(_) => println!("{}成功: {} ({}环境)", action.label(), module.name, env),
+                    Err(e) => eprintln!("{}失败 {} ({}环境): {}", action.label(), module.name, env, e),
+                }
+            });
+            handles.push(handle);
+        }
+    }
+
+    // 等待所有线程完成
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use crate::config::{DeployConfig, Module};
+use crate::upload::{check_health, connect, execute_remote_query, find_pids, shell_quote};
+use serde::Serialize;
+use ssh2::Session;
+use std::thread;
+use unicode_width::UnicodeWidthStr;
+
+/// 单个模块的运行状态
+#[derive(Serialize)]
+pub struct ModuleStatus {
+    /// 部署环境
+    pub env: String,
+    /// 模块名称
+    pub module: String,
+    /// 远程jar包路径
+    pub jar_path: String,
+    /// 进程id,未运行时为空
+    pub pid: Option<String>,
+    /// 进程运行时长
+    pub uptime: Option<String>,
+    /// 常驻内存(MB)
+    pub rss_mb: Option<f64>,
+    /// jar包修改时间
+    pub modified: Option<String>,
+    /// jar包sha256
+    pub sha256: Option<String>,
+    /// 健康检查结果
+    pub health: Option<String>,
+    /// 查询失败原因
+    pub error: Option<String>,
+}
+
+impl ModuleStatus {
+    fn new(env: &str, module: &str, jar_path: &str) -> Self {
+        ModuleStatus {
+            env: env.to_string(),
+            module: module.to_string(),
+            jar_path: jar_path.to_string(),
+            pid: None,
+            uptime: None,
+            rss_mb: None,
+            modified: None,
+            sha256: None,
+            health: None,
+            error: None,
+        }
+    }
+}
+
+/// 查询各环境下所有模块的运行状态并输出
+pub fn show_status(
+    config_path: &str,
+    environments: &[String],
+    models: &[String],
+    json: bool,
+) -> Result<(), String> {
+    // 每个环境一个线程并行查询
+    let mut handles = vec![];
+
+    for env in environments {
+        let env = env.to_string();
+        let models = models.to_vec();
+
+        let config = match DeployConfig::from_file(config_path, &env) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("加载{}环境配置失败: {}", env, e);
+                continue;
+            }
+        };
+
+        let handle = thread::spawn(move || query_env_status(&config, &env, &models));
+        handles.push(handle);
+    }
+
+    let mut statuses = vec![];
+    for handle in handles {
+        statuses.extend(handle.join().unwrap());
+    }
+
+    if json {
+        let output = serde_json::to_string_pretty(&statuses)
+            .map_err(|e| format!("序列化状态信息失败: {}", e))?;
+        println!("{}", output);
+    } else {
+        print_table(&statuses);
+    }
+    Ok(())
+}
+
+/// 查询单个环境下所有模块的运行状态
+fn query_env_status(config: &DeployConfig, env: &str, models: &[String]) -> Vec<ModuleStatus> {
+    let modules: Vec<Module> = match config.modules() {
+        Ok(modules) => modules
+            .into_iter()
+            .filter(|module| models.is_empty() || models.contains(&module.name))
+            .collect(),
+        Err(e) => {
+            let mut status = ModuleStatus::new(env, "-", "-");
+            status.error = Some(e);
+            return vec![status];
+        }
+    };
+
+    let sess = match connect(config) {
+        Ok(sess) => sess,
+        Err(e) => {
+            return modules
+                .iter()
+                .map(|module| {
+                    let mut status = ModuleStatus::new(env, &module.name, &module.remote_path);
+                    status.error = Some(e.clone());
+                    status
+                })
+                .collect();
+        }
+    };
+
+    modules
+        .iter()
+        .map(|module| {
+            let mut status = ModuleStatus::new(env, &module.name, &module.remote_path);
+            if let Err(e) = query_module_status(&sess, module, &mut status) {
+                status.error = Some(e);
+            }
+            status
+        })
+        .collect()
+}
+
+/// 查询单个模块的进程、jar包和健康检查信息
+fn query_module_status(
+    sess: &Session,
+    module: &Module,
+    status: &mut ModuleStatus,
+) -> Result<(), String> {
+    // 只有文件确实不存在时才提示不存在,权限不足、缺少sha256sum等错误原样输出
+    let jar_path = shell_quote(&status.jar_path);
+    if execute_remote_query(sess, &format!("test -e {}", jar_path)).is_err() {
+        return Err(format!("远程jar包不存在: {}", status.jar_path));
+    }
+    let stat_cmd = format!(
+        "stat -c '%y' {} && sha256sum {} | awk '{{print $1}}'",
+        jar_path, jar_path
+    );
+    let stat = execute_remote_query(sess, &stat_cmd)
+        .map_err(|e| format!("读取远程jar包信息失败 {}: {}", status.jar_path, e.trim()))?;
+    let mut lines = stat.lines();
+    status.modified = lines
+        .next()
+        .map(|line| line.split('.').next().unwrap_or(line).to_string());
+    status.sha256 = lines.next().map(|line| line.trim().to_string());
+
+    let pids = find_pids(sess, &status.jar_path)?;
+    let pid = match pids.split_whitespace().next() {
+        Some(pid) => pid.to_string(),
+        None => return Ok(()),
+    };
+
+    let ps_cmd = format!("ps -o etime=,rss= -p {}", pid);
+    let ps = execute_remote_query(sess, &ps_cmd)?;
+    let mut fields = ps.split_whitespace();
+    status.uptime = fields.next().map(|uptime| uptime.to_string());
+    status.rss_mb = fields
+        .next()
+        .and_then(|rss| rss.parse::<f64>().ok())
+        .map(|rss_kb| rss_kb / 1024.0);
+    status.pid = Some(pid);
+
+    if let Some(url) = &module.health_check {
+        status.health = Some(match check_health(sess, url) {
+            Ok(code) => format!("UP ({})", code),
+            Err(e) => format!("DOWN ({})", e),
+        });
+    }
+
+    Ok(())
+}
+
+/// 以表格形式输出状态信息
+fn print_table(statuses: &[ModuleStatus]) {
+    let headers = [
+        "环境", "模块", "PID", "运行时长", "内存(MB)", "修改时间", "SHA256", "健康检查",
+    ];
+    let rows: Vec<Vec<String>> = statuses
+        .iter()
+        .map(|status| {
+            let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+            let pid = match (&status.pid, &status.error) {
+                (_, Some(e)) => format!("错误: {}", e),
+                (Some(pid), None) => pid.clone(),
+                (None, None) => "未运行".to_string(),
+            };
+            vec![
+                status.env.clone(),
+                status.module.clone(),
+                pid,
+                or_dash(&status.uptime),
+                status
+                    .rss_mb
+                    .map(|rss| format!("{:.1}", rss))
+                    .unwrap_or_else(|| "-".to_string()),
+                or_dash(&status.modified),
+                status
+                    .sha256
+                    .as_ref()
+                    .map(|hash| hash.chars().take(12).collect())
+                    .unwrap_or_else(|| "-".to_string()),
+                or_dash(&status.health),
+            ]
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = headers.iter().map(|header| display_width(header)).collect();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(display_width(cell));
+        }
+    }
+
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{}{}", cell, " ".repeat(widths[i] - display_width(cell))))
+            .collect::<Vec<String>>()
+            .join("  ")
+    };
+
+    println!("{}", format_row(headers.iter().map(|header| header.to_string()).collect()));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
+
+/// 计算字符串在终端中的显示宽度,中文等宽字符按2计算
+fn display_width(value: &str) -> usize {
+    UnicodeWidthStr::width(value)
+}
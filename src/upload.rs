@@ -1,4 +1,5 @@
-use ssh2::Session;
+use crate::config::{DeployConfig, Module};
+use ssh2::{Channel, PtyModeOpcode, PtyModes, Session};
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::Path;
@@ -9,6 +10,61 @@ use std::fs;
 const MAX_RETRIES: u32 = 3;
 /// 重试间隔(秒)
 const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// 启动后健康检查最大次数
+const HEALTH_CHECK_RETRIES: u32 = 30;
+/// 健康检查间隔(秒)
+const HEALTH_CHECK_DELAY: Duration = Duration::from_secs(2);
+
+/// 远程命令提权选项
+#[derive(Clone, Default)]
+pub struct SudoOptions {
+    /// 是否启用sudo
+    pub enabled: bool,
+    /// sudo切换到的运行用户
+    pub run_as: Option<String>,
+    /// sudo密码
+    pub password: Option<String>,
+}
+
+impl SudoOptions {
+    pub fn from_config(config: &DeployConfig) -> Self {
+        SudoOptions {
+            enabled: config.sudo || config.run_as.is_some(),
+            run_as: config.run_as.clone(),
+            password: config.sudo_password.clone(),
+        }
+    }
+
+    /// 文件操作(备份、复制、解压)使用的选项: 以root执行,只有启动和停止服务时切换到run_as
+    pub fn as_root(&self) -> SudoOptions {
+        SudoOptions {
+            run_as: None,
+            ..self.clone()
+        }
+    }
+
+    /// 将命令包装为sudo执行
+    fn wrap(&self, command: &str) -> String {
+        if !self.enabled {
+            return command.to_string();
+        }
+        // 有密码时通过-S从输入读取密码,否则使用-n避免卡在密码提示
+        let mut sudo = if self.password.is_some() {
+            "sudo -S -p ''".to_string()
+        } else {
+            "sudo -n".to_string()
+        };
+        if let Some(user) = &self.run_as {
+            sudo.push_str(&format!(" -u {}", shell_quote(user)));
+        }
+        format!("{} sh -c {}", sudo, shell_quote(command))
+    }
+}
+
+/// 使用单引号转义shell参数
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
 
 /// 将字节转换为 MB
 fn bytes_to_mb(bytes: u64) -> f64 {
@@ -16,7 +72,7 @@ fn bytes_to_mb(bytes: u64) -> f64 {
 }
 
 /// 创建SSH会话
-fn create_ssh_session(server: &str, username: &str, password: &str) -> Result<Session, String> {
+pub fn create_ssh_session(server: &str, username: &str, password: &str) -> Result<Session, String> {
     let tcp = TcpStream::connect(server).map_err(|e| format!("ssh通信连接失败: {}", e))?;
     let mut sess = Session::new().map_err(|e| format!("创建ssh会话失败: {}", e))?;
     sess.set_tcp_stream(tcp);
@@ -28,6 +84,19 @@ fn create_ssh_session(server: &str, username: &str, password: &str) -> Result<Se
     Ok(sess)
 }
 
+/// 按环境配置创建SSH会话（带重试机制）
+pub fn connect(config: &DeployConfig) -> Result<Session, String> {
+    (0..MAX_RETRIES)
+        .find_map(|attempt| {
+            if attempt > 0 {
+                eprintln!("尝试重新创建SSH会话 (第{}次重试)...", attempt);
+                std::thread::sleep(RETRY_DELAY);
+            }
+            create_ssh_session(&config.server, &config.username, &config.password).ok()
+        })
+        .ok_or_else(|| format!("创建SSH会话失败，已达到最大重试次数({}次)", MAX_RETRIES))
+}
+
 /// 读取本地文件
 fn read_local_file(local_path: &str) -> Result<(Vec<u8>, u64), String> {
     println!("读取本地文件: {}", local_path);
@@ -56,6 +125,7 @@ fn read_local_file(local_path: &str) -> Result<(Vec<u8>, u64), String> {
 /// 上传文件到远程服务器
 fn upload_to_remote(
     sess: &Session,
+    sudo: &SudoOptions,
     data: &[u8],
     file_size: u64,
     remote_path: &str,
@@ -69,7 +139,7 @@ fn upload_to_remote(
                 println!("远程文件已存在: {}", remote_path);
                 // 删除已存在的文件
                 let remove_cmd = format!("mv {} {}.bak", remote_path,remote_path);
-                execute_remote_command(sess, &remove_cmd)
+                execute_privileged_command(sess, sudo, &remove_cmd)
                     .map_err(|e| format!("标记远程文件为bak备份文件失败: {}", e))?;
                 println!("已标记备份存在的文件,{}.bak",remote_path);
             }
@@ -83,8 +153,36 @@ fn upload_to_remote(
     }
 
 
+    // 启用sudo时登录用户可能没有目标目录的写权限,先上传到只有登录用户可写的临时目录再以root复制过去
+    if !sudo.enabled {
+        return send_file(sess, data, file_size, remote_path);
+    }
+    let file_name = Path::new(remote_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("upload");
+    let staging_dir = execute_remote_query(sess, "mktemp -d /tmp/deploy-tool.XXXXXXXXXX")
+        .map(|output| output.trim().to_string())
+        .map_err(|e| format!("创建远程临时目录失败: {}", e))?;
+    let staging_path = format!("{}/{}", staging_dir, file_name);
+
+    let result = send_file(sess, data, file_size, &staging_path).and_then(|_| {
+        let copy_cmd = format!("cp {} {}", shell_quote(&staging_path), shell_quote(remote_path));
+        execute_privileged_command(sess, sudo, &copy_cmd)
+            .map(|_| ())
+            .map_err(|e| format!("复制临时文件到目标路径失败: {}", e))
+    });
+    // 无论成功与否都删除临时目录
+    if let Err(e) = execute_remote_query(sess, &format!("rm -rf {}", shell_quote(&staging_dir))) {
+        eprintln!("删除远程临时目录失败 {}: {}", staging_dir, e);
+    }
+    result
+}
+
+/// 通过scp把数据写入远程文件
+fn send_file(sess: &Session, data: &[u8], file_size: u64, path: &str) -> Result<(), String> {
     let mut remote_file = sess
-        .scp_send(Path::new(remote_path), 0o644, file_size, None)
+        .scp_send(Path::new(path), 0o644, file_size, None)
         .map_err(|e| format!("创建远程文件失败: {}", e))?;
 
     remote_file
@@ -102,27 +200,81 @@ fn upload_to_remote(
     remote_file
         .wait_close()
         .map_err(|e| format!("等待远程文件关闭失败: {}", e))?;
-
     Ok(())
 }
 
 /// 在远程服务器执行命令并返回输出
-fn execute_remote_command(sess: &Session, command: &str) -> Result<String, String> {
+pub fn execute_remote_command(sess: &Session, command: &str) -> Result<String, String> {
+    println!("执行远程命令: {}", command);
+    execute_remote_query(sess, command)
+}
+
+/// 在远程服务器执行查询类命令,不打印命令内容
+pub fn execute_remote_query(sess: &Session, command: &str) -> Result<String, String> {
     let mut channel = sess
         .channel_session()
         .map_err(|e| format!("创建SSH通道失败: {}", e))?;
 
-    println!("执行远程命令: {}", command);
-
     channel
         .exec(command)
         .map_err(|e| format!("执行远程命令失败: {}", e))?;
 
+    read_channel_output(channel)
+}
+
+/// 按sudo配置在远程服务器执行命令,未启用sudo时等同于execute_remote_command
+fn execute_privileged_command(
+    sess: &Session,
+    sudo: &SudoOptions,
+    command: &str,
+) -> Result<String, String> {
+    println!("执行远程命令: {}", sudo.wrap(command));
+    execute_privileged_query(sess, sudo, command)
+}
+
+/// 按sudo配置在远程服务器执行查询类命令,不打印命令内容
+pub fn execute_privileged_query(
+    sess: &Session,
+    sudo: &SudoOptions,
+    command: &str,
+) -> Result<String, String> {
+    let command = sudo.wrap(command);
+    let password = match &sudo.password {
+        Some(password) if sudo.enabled => password,
+        _ => return execute_remote_query(sess, &command),
+    };
+
+    let mut channel = sess
+        .channel_session()
+        .map_err(|e| format!("创建SSH通道失败: {}", e))?;
+
+    // 分配PTY以兼容开启了requiretty的服务器,关闭回显避免密码出现在输出中
+    let mut modes = PtyModes::new();
+    modes.set_boolean(PtyModeOpcode::ECHO, false);
+    channel
+        .request_pty("vt100", Some(modes), None)
+        .map_err(|e| format!("申请PTY失败: {}", e))?;
+
+    channel
+        .exec(&command)
+        .map_err(|e| format!("执行远程命令失败: {}", e))?;
+    channel
+        .write_all(format!("{}\n", password).as_bytes())
+        .map_err(|e| format!("写入sudo密码失败: {}", e))?;
+
+    // PTY会把换行转换为\r\n
+    read_channel_output(channel).map(|output| output.replace("\r\n", "\n"))
+}
+
+/// 读取通道输出并检查退出状态
+fn read_channel_output(mut channel: Channel) -> Result<String, String> {
     // 读取命令输出
     let mut output = String::new();
     channel
         .read_to_string(&mut output)
         .map_err(|e| format!("读取命令输出失败: {}", e))?;
+    let mut error = String::new();
+    let _ = channel.stderr().read_to_string(&mut error);
 
     channel
         .wait_close()
@@ -134,20 +286,45 @@ fn execute_remote_command(sess: &Session, command: &str) -> Result<String, Strin
         .map_err(|e| format!("获取退出状态失败: {}", e))?;
 
     if exit_status != 0 {
-        return Err(format!("远程命令执行失败，退出状态: {}", exit_status));
+        let error = error.trim();
+        if error.is_empty() {
+            return Err(format!("远程命令执行失败，退出状态: {}", exit_status));
+        }
+        return Err(format!("远程命令执行失败，退出状态: {}: {}", exit_status, error));
     }
 
     Ok(output)
 }
 
-/// 杀死远程服务器上的进程
-fn kill_process(sess: &Session, jar_path: &str, env: &str) -> Result<(), String> {
-    // 1. 先获取进程ID列表
+/// 查找运行指定JAR包的进程ID,多个进程以换行分隔
+pub fn find_pids(sess: &Session, jar_path: &str) -> Result<String, String> {
     let find_pid_cmd = format!(
         "ps -ef | grep {} | grep -v grep | awk '{{print $2}}'",
         jar_path
     );
-    let pids = execute_remote_command(sess, &find_pid_cmd)?;
+    execute_remote_query(sess, &find_pid_cmd)
+}
+
+/// 在服务器上通过curl访问健康检查地址,返回HTTP状态码
+pub fn check_health(sess: &Session, url: &str) -> Result<String, String> {
+    let check_cmd = format!(
+        "curl -s -o /dev/null -w '%{{http_code}}' --max-time 5 {}",
+        shell_quote(url)
+    );
+    let code = execute_remote_query(sess, &check_cmd)
+        .map_err(|e| format!("健康检查请求失败: {}", e))?;
+    let code = code.trim().to_string();
+    if code.starts_with('2') {
+        Ok(code)
+    } else {
+        Err(format!("健康检查返回状态码: {}", code))
+    }
+}
+
+/// 杀死远程服务器上的进程
+fn kill_process(sess: &Session, sudo: &SudoOptions, jar_path: &str, env: &str) -> Result<(), String> {
+    // 1. 先获取进程ID列表
+    let pids = find_pids(sess, jar_path)?;
 
     if pids.trim().is_empty() {
         // 没有找到进程，说明已经不存在
@@ -161,7 +338,7 @@ fn kill_process(sess: &Session, jar_path: &str, env: &str) -> Result<(), String>
     } else {
         format!("kill -9 {}", pids.trim())
     };
-    let output = execute_remote_command(sess, &kill_cmd)?;
+    let output = execute_privileged_command(sess, sudo, &kill_cmd)?;
 
     if !output.trim().is_empty() {
         println!("杀死进程命令输出: {}", output);
@@ -204,7 +381,7 @@ fn kill_process(sess: &Session, jar_path: &str, env: &str) -> Result<(), String>
 
         // 直接发送 kill -9 命令
         let force_kill_cmd = format!("kill -9 {}", pids.trim());
-        match execute_remote_command(sess, &force_kill_cmd) {
+        match execute_privileged_command(sess, sudo, &force_kill_cmd) {
             Ok(output) => {
                 if !output.trim().is_empty() {
                     println!("强制杀死命令输出: {}", output);
@@ -231,25 +408,33 @@ fn kill_process(sess: &Session, jar_path: &str, env: &str) -> Result<(), String>
 }
 
 /// 启动JAR包并检查进程状态
-fn start_jar(sess: &Session, jar_path: &str, java_path: &str, env: &str) -> Result<(), String> {
+fn start_jar(
+    sess: &Session,
+    sudo: &SudoOptions,
+    java_path: &str,
+    module: &Module,
+    env: &str,
+) -> Result<(), String> {
+    let jar_path = module.remote_path.as_str();
+
     // 启动JAR包
-    let start_cmd = format!(
-        "nohup {} -jar {} --spring.profiles.active={} > /dev/null 2>&1 &",
-        java_path, jar_path, env
-    );
+    let mut start_cmd = format!("nohup {}", java_path);
+    if let Some(jvm_opts) = &module.jvm_opts {
+        start_cmd.push_str(&format!(" {}", jvm_opts));
+    }
+    start_cmd.push_str(&format!(" -jar {} --spring.profiles.active={}", jar_path, env));
+    if let Some(port) = module.port {
+        start_cmd.push_str(&format!(" --server.port={}", port));
+    }
+    start_cmd.push_str(" > /dev/null 2>&1 &");
 
-    execute_remote_command(sess, &start_cmd)?;
+    execute_privileged_command(sess, sudo, &start_cmd)?;
 
     // 等待一小段时间确保进程已启动
     std::thread::sleep(Duration::from_secs(2));
 
     // 检查进程是否成功启动
-    let check_cmd = format!(
-        "ps -ef | grep {} | grep -v grep | awk '{{print $2}}'",
-        jar_path
-    );
-
-    let output = execute_remote_command(sess, &check_cmd)?;
+    let output = find_pids(sess, jar_path)?;
 
     if output.trim().is_empty() {
         return Err(format!("程序启动失败: {}", jar_path));
@@ -260,31 +445,117 @@ fn start_jar(sess: &Session, jar_path: &str, java_path: &str, env: &str) -> Resu
         jar_path,
         output.trim()
     );
+
+    if let Some(url) = &module.health_check {
+        wait_for_health(sess, url)?;
+    }
     Ok(())
 }
 
+/// 轮询健康检查地址直到服务可用
+fn wait_for_health(sess: &Session, url: &str) -> Result<(), String> {
+    println!("等待健康检查通过: {}", url);
+    let mut last_error = String::new();
+    for attempt in 0..HEALTH_CHECK_RETRIES {
+        if attempt > 0 {
+            std::thread::sleep(HEALTH_CHECK_DELAY);
+        }
+        match check_health(sess, url) {
+            Ok(code) => {
+                println!("健康检查通过: {} ({})", url, code);
+                return Ok(());
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(format!(
+        "健康检查未通过，已达到最大检查次数({}次): {}",
+        HEALTH_CHECK_RETRIES, last_error
+    ))
+}
+
+/// 停止远程服务器上运行的JAR包
+pub fn stop_jar(config: &DeployConfig, remote_path: &str, env: &str) -> Result<(), String> {
+    let sudo = SudoOptions::from_config(config);
+    let sess = connect(config)?;
+
+    (0..MAX_RETRIES)
+        .find_map(|attempt| {
+            if attempt > 0 {
+                println!("尝试重新杀死进程 (第{}次重试)...", attempt);
+                std::thread::sleep(RETRY_DELAY);
+            }
+            kill_process(&sess, &sudo, remote_path, env).ok()
+        })
+        .ok_or_else(|| format!("进程杀死失败，已达到最大重试次数({}次)", MAX_RETRIES))
+}
+
+/// 启动远程服务器上已存在的JAR包,已在运行时不重复启动
+pub fn run_jar(config: &DeployConfig, module: &Module, env: &str) -> Result<(), String> {
+    let remote_path = module.remote_path.as_str();
+    let sudo = SudoOptions::from_config(config);
+    let sess = connect(config)?;
+
+    let check_path_cmd = format!("test -e {} && echo 'exists' || echo 'not exists'", remote_path);
+    if execute_remote_query(&sess, &check_path_cmd)?.trim() != "exists" {
+        return Err(format!("远程jar包不存在: {}", remote_path));
+    }
+
+    let pids = find_pids(&sess, remote_path)?;
+    if !pids.trim().is_empty() {
+        println!("程序已在运行: {},进程id {}", remote_path, pids.trim());
+        return Ok(());
+    }
+
+    start_jar(&sess, &sudo, &config.java_path, module, env)
+}
+
+/// 回滚到上一次部署的JAR包: 与.bak备份互换后重启,再次回滚即可恢复
+pub fn rollback_jar(config: &DeployConfig, module: &Module, env: &str) -> Result<(), String> {
+    let remote_path = module.remote_path.as_str();
+    let sudo = SudoOptions::from_config(config);
+    let sess = connect(config)?;
+
+    let backup_path = format!("{}.bak", remote_path);
+    let check_path_cmd = format!("test -e {} && echo 'exists' || echo 'not exists'", backup_path);
+    if execute_remote_query(&sess, &check_path_cmd)?.trim() != "exists" {
+        return Err(format!("没有可回滚的备份文件: {}", backup_path));
+    }
+
+    (0..MAX_RETRIES)
+        .find_map(|attempt| {
+            if attempt > 0 {
+                println!("尝试重新杀死进程 (第{}次重试)...", attempt);
+                std::thread::sleep(RETRY_DELAY);
+            }
+            kill_process(&sess, &sudo, remote_path, env).ok()
+        })
+        .ok_or_else(|| format!("进程杀死失败，已达到最大重试次数({}次)", MAX_RETRIES))?;
+
+    let swap_cmd = format!(
+        "mv {0} {0}.rollback && mv {1} {0} && mv {0}.rollback {1}",
+        remote_path, backup_path
+    );
+    execute_privileged_command(&sess, &sudo.as_root(), &swap_cmd)
+        .map_err(|e| format!("恢复备份文件失败: {}", e))?;
+    println!("已恢复备份文件: {} -> {}", backup_path, remote_path);
+
+    start_jar(&sess, &sudo, &config.java_path, module, env)
+}
+
 /// 上传并运行 JAR 包（整合上传和运行功能）
 pub fn upload_and_run_jar(
-    server: &str,
-    username: &str,
-    password: &str,
+    config: &DeployConfig,
+    module: &Module,
     local_path: &str,
-    remote_path: &str,
-    java_path: &str,
     env: &str,
 ) -> Result<(), String> {
+    let remote_path = module.remote_path.as_str();
     // 读取本地文件
     let (data, file_size) = read_local_file(local_path)?;
+    let sudo = SudoOptions::from_config(config);
 
-    let sess = (0..MAX_RETRIES)
-        .find_map(|attempt| {
-            if attempt > 0 {
-                println!("尝试重新创建SSH会话 (第{}次重试)...", attempt);
-                std::thread::sleep(RETRY_DELAY);
-            }
-            create_ssh_session(server, username, password).ok()
-        })
-        .ok_or_else(|| format!("创建SSH会话失败，已达到最大重试次数({}次)", MAX_RETRIES))?;
+    let sess = connect(config)?;
 
     // 上传文件（带重试机制）
     (0..MAX_RETRIES)
@@ -293,7 +564,7 @@ pub fn upload_and_run_jar(
                 println!("尝试重新上传文件 (第{}次重试)...", attempt);
                 std::thread::sleep(RETRY_DELAY);
             }
-            upload_to_remote(&sess, &data, file_size, remote_path).ok()
+            upload_to_remote(&sess, &sudo.as_root(), &data, file_size, remote_path).ok()
         })
         .ok_or_else(|| format!("文件上传失败，已达到最大重试次数({}次)", MAX_RETRIES))?;
 
@@ -311,12 +582,12 @@ pub fn upload_and_run_jar(
                 println!("尝试重新杀死进程 (第{}次重试)...", attempt);
                 std::thread::sleep(RETRY_DELAY);
             }
-            kill_process(&sess, remote_path, &env).ok()
+            kill_process(&sess, &sudo, remote_path, env).ok()
         })
         .ok_or_else(|| format!("进程杀死失败，已达到最大重试次数({}次)", MAX_RETRIES))?;
 
     // 启动JAR包
-    start_jar(&sess, remote_path, java_path, env)?;
+    start_jar(&sess, &sudo, &config.java_path, module, env)?;
 
     println!("{}环境JAR包部署和启动成功: {}", env, remote_path);
     Ok(())
@@ -324,33 +595,35 @@ pub fn upload_and_run_jar(
 
 /// 上传zip文件
 pub fn upload_file(
-    server: &str,
-    username: &str,
-    password: &str,
+    config: &DeployConfig,
     local_path: &str,
     remote_path: &str,
 ) -> Result<(), String> {
     // 读取本地文件
     let (data, file_size) = read_local_file(local_path)?;
+    let sudo = SudoOptions::from_config(config).as_root();
 
     // 创建SSH会话
-    let sess = create_ssh_session(server, username, password)?;
+    let sess = create_ssh_session(&config.server, &config.username, &config.password)?;
 
     // 构建远程zip路径
     let remote_zip_path = format!("{}.zip", remote_path);
 
     // 上传文件
-    upload_to_remote(&sess, &data, file_size, &remote_zip_path)?;
+    upload_to_remote(&sess, &sudo, &data, file_size, &remote_zip_path)?;
 
     // 解压命令：先删除目标目录，然后解压zip文件
     // 使用-o选项覆盖现有文件，不提示
     let unzip_cmd = format!(
         "rm -rf {} && mkdir -p {} && cd {} && /usr/bin/unzip -o {}",
-        remote_path, remote_path, remote_path, remote_zip_path
+        shell_quote(remote_path),
+        shell_quote(remote_path),
+        shell_quote(remote_path),
+        shell_quote(&remote_zip_path)
     );
 
     // 执行解压命令
-    execute_remote_command(&sess, &unzip_cmd)?;
+    execute_privileged_command(&sess, &sudo, &unzip_cmd)?;
 
     println!(
         "文件上传成功! {} -> {} (大小: {:.2} MB)",
@@ -360,3 +633,49 @@ pub fn upload_file(
     );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sudo(password: Option<&str>, run_as: Option<&str>) -> SudoOptions {
+        SudoOptions {
+            enabled: true,
+            run_as: run_as.map(str::to_string),
+            password: password.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn wrap_builds_sudo_command() {
+        let command = "kill 1 && echo 'done'";
+        assert_eq!(SudoOptions::default().wrap(command), command);
+        assert_eq!(
+            sudo(None, None).wrap(command),
+            "sudo -n sh -c 'kill 1 && echo '\\''done'\\'''"
+        );
+        assert_eq!(
+            sudo(Some("secret"), None).wrap("true"),
+            "sudo -S -p '' sh -c 'true'"
+        );
+        assert_eq!(
+            sudo(None, Some("app")).wrap("true"),
+            "sudo -n -u 'app' sh -c 'true'"
+        );
+        assert_eq!(
+            sudo(Some("secret"), Some("app")).wrap("true"),
+            "sudo -S -p '' -u 'app' sh -c 'true'"
+        );
+    }
+
+    #[test]
+    fn file_operations_run_as_root() {
+        let root = sudo(Some("secret"), Some("app")).as_root();
+        assert!(root.enabled);
+        assert_eq!(root.wrap("true"), "sudo -S -p '' sh -c 'true'");
+        assert_eq!(
+            sudo(None, Some("app")).as_root().wrap("true"),
+            "sudo -n sh -c 'true'"
+        );
+    }
+}
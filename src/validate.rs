@@ -0,0 +1,452 @@
+use crate::config::{ConfigFormat, DeployConfig, RawEnvironments};
+use std::collections::HashSet;
+use std::fs;
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// 配置文件中的一个问题
+struct Problem {
+    /// 问题所在行号,无法定位时为空
+    line: Option<usize>,
+    message: String,
+}
+
+/// 部署前校验配置文件,一次性报告所有问题
+///
+/// `java_envs` 中的环境会额外检查模块配置,`vue_envs` 中的环境会额外检查前端打包配置,
+/// 两者都为空时校验配置文件中的所有环境。
+pub fn validate_config(
+    config_path: &str,
+    java_envs: &[String],
+    vue_envs: &[String],
+) -> Result<(), String> {
+    let content = fs::read_to_string(config_path)
+        .map_err(|e| format!("无法读取配置文件 {}: {}", config_path, e))?;
+    let problems = check_content(&content, ConfigFormat::from_path(config_path), java_envs, vue_envs);
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut report = format!("配置文件校验失败 {} (共{}个问题):", config_path, problems.len());
+    for problem in problems {
+        match problem.line {
+            Some(line) => report.push_str(&format!("\n  第{}行: {}", line, problem.message)),
+            None => report.push_str(&format!("\n  {}", problem.message)),
+        }
+    }
+    Err(report)
+}
+
+fn check_content(
+    content: &str,
+    format: ConfigFormat,
+    java_envs: &[String],
+    vue_envs: &[String],
+) -> Vec<Problem> {
+    // 只有TOML格式能定位到行号,YAML和JSON的语法错误信息中自带位置
+    let doc = match format {
+        ConfigFormat::Toml => match ImDocument::parse(content) {
+            Ok(doc) => Some(doc),
+            Err(e) => {
+                return vec![Problem {
+                    line: e.span().map(|span| line_of(content, span.start)),
+                    message: format!("TOML语法错误: {}", e.message()),
+                }]
+            }
+        },
+        _ => None,
+    };
+    let root = match format.parse(content) {
+        Ok(root) => root,
+        Err(e) => return vec![Problem { line: None, message: e }],
+    };
+    let raw = match RawEnvironments::parse(content, format) {
+        Ok(raw) => raw,
+        Err(e) => return vec![Problem { line: None, message: e }],
+    };
+    let locator = Locator {
+        content,
+        doc: doc.as_ref(),
+    };
+    let mut problems = vec![];
+
+    for key in root.keys() {
+        if key != "defaults" && key != "environments" {
+            problems.push(Problem {
+                line: locator.find(&[], &[key]).map(|(line, _)| line),
+                message: format!("未知的顶层配置项 {}", key),
+            });
+        }
+    }
+
+    let check_all = java_envs.is_empty() && vue_envs.is_empty();
+    let environments: Vec<String> = if check_all {
+        raw.names()
+    } else {
+        let mut names: Vec<String> = java_envs.iter().chain(vue_envs).cloned().collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+
+    for env in &environments {
+        let chain = locator.chain(env);
+        let env_line = locator
+            .find(&chain[..chain.len().min(1)], &[])
+            .map(|(line, _)| line);
+        // 问题归属到实际定义该配置项的表,继承来的问题只报告一次
+        let report = |problems: &mut Vec<Problem>, path: &[&str], message: String| {
+            let (line, section) = match locator.find(&chain, path) {
+                Some((line, section)) => (Some(line), section),
+                None => (env_line, format!("environments.{}", env)),
+            };
+            problems.push(Problem {
+                line,
+                message: format!("[{}] {}", section, message),
+            });
+        };
+
+        let merged = match raw.merged(env) {
+            Ok(merged) => merged,
+            Err(e) => {
+                report(&mut problems, &[], e);
+                continue;
+            }
+        };
+
+        // 收集反序列化时未使用的配置项
+        let mut unknown = vec![];
+        let config: Result<DeployConfig, _> =
+            serde_ignored::deserialize(toml::Value::Table(merged), |path| {
+                unknown.push(path.to_string())
+            });
+        for path in unknown {
+            let segments: Vec<&str> = path.split('.').collect();
+            report(&mut problems, &segments, format!("未知的配置项 {}", path));
+        }
+        let config = match config.map_err(|e| e.to_string()).and_then(|c| c.resolve_env_vars()) {
+            Ok(config) => config,
+            Err(e) => {
+                report(&mut problems, &[], e.trim().replace('\n', " "));
+                continue;
+            }
+        };
+
+        if let Err(e) = check_server(&config.server) {
+            report(&mut problems, &["server"], e);
+        }
+        if !config.remote_base_path.starts_with('/') {
+            report(
+                &mut problems,
+                &["remote_base_path"],
+                format!("remote_base_path必须是绝对路径: {}", config.remote_base_path),
+            );
+        }
+        for (i, module) in config.modules.iter().enumerate() {
+            let index = i.to_string();
+            if let Some(remote_dir) = &module.remote_dir {
+                if !remote_dir.starts_with('/') {
+                    report(
+                        &mut problems,
+                        &["modules", &index, "remote_dir"],
+                        format!("{}模块的remote_dir必须是绝对路径: {}", module.name, remote_dir),
+                    );
+                }
+            }
+        }
+
+        if let Some(web_remote_path) = &config.web_remote_path {
+            if !is_web_remote_path(web_remote_path) {
+                report(
+                    &mut problems,
+                    &["web_remote_path"],
+                    format!("web_remote_path必须是根目录以外的绝对路径: {}", web_remote_path),
+                );
+            }
+        }
+        for (i, app) in config.web_apps.iter().enumerate() {
+            if let Some(remote_path) = &app.remote_path {
+                if !is_web_remote_path(remote_path) {
+                    report(
+                        &mut problems,
+                        &["web_apps", &i.to_string(), "remote_path"],
+                        format!("{}应用的remote_path必须是根目录以外的绝对路径: {}", app.name, remote_path),
+                    );
+                }
+            }
+        }
+
+        if check_all || java_envs.contains(env) {
+            match config.modules() {
+                Ok(modules) if modules.is_empty() => {
+                    report(&mut problems, &["jar_files"], "模块列表为空".to_string());
+                }
+                Ok(modules) => {
+                    let mut seen = HashSet::new();
+                    for (i, module) in modules.iter().enumerate() {
+                        if module.name.is_empty() {
+                            report(&mut problems, &["modules", &i.to_string()], "模块名称为空".to_string());
+                        } else if !seen.insert(module.name.clone()) {
+                            let path: Vec<String> = if config.modules.is_empty() {
+                                vec!["jar_files".to_string()]
+                            } else {
+                                vec!["modules".to_string(), i.to_string(), "name".to_string()]
+                            };
+                            let path: Vec<&str> = path.iter().map(|s| s.as_str()).collect();
+                            report(&mut problems, &path, format!("模块重复: {}", module.name));
+                        }
+                    }
+                }
+                // 只有jar_files模式会失败,且仅在检查所有环境时才可能是前端环境
+                Err(e) if !check_all => report(&mut problems, &[], e),
+                Err(_) => {}
+            }
+            if config.java_path.is_empty() {
+                report(&mut problems, &["java_path"], "java_path不能为空".to_string());
+            }
+        }
+
+        if vue_envs.contains(env) && config.web_apps.is_empty() {
+            if config.scripts.is_empty() {
+                report(&mut problems, &["scripts"], "scripts不能为空".to_string());
+            }
+            if config.output_dir.is_empty() {
+                report(&mut problems, &["output_dir"], "output_dir不能为空".to_string());
+            }
+        }
+        if vue_envs.contains(env) && !config.web_apps.is_empty() {
+            let mut seen = HashSet::new();
+            for (i, app) in config.web_apps().iter().enumerate() {
+                let index = i.to_string();
+                if app.name.is_empty() {
+                    report(&mut problems, &["web_apps", &index], "web应用名称为空".to_string());
+                } else if !is_plain_name(&app.name) {
+                    // 名称会作为默认部署目录的最后一级,不能包含/、.或..
+                    report(
+                        &mut problems,
+                        &["web_apps", &index, "name"],
+                        format!("web应用名称只能包含字母、数字、_和-: {}", app.name),
+                    );
+                } else if !seen.insert(app.name.clone()) {
+                    report(&mut problems, &["web_apps", &index, "name"], format!("web应用重复: {}", app.name));
+                }
+                // 应用未配置时继承环境的scripts和output_dir
+                if app.scripts.is_empty() {
+                    report(&mut problems, &["web_apps", &index], format!("{}应用的scripts不能为空", app.name));
+                }
+                if app.output_dir.is_empty() {
+                    report(&mut problems, &["web_apps", &index], format!("{}应用的output_dir不能为空", app.name));
+                }
+            }
+        }
+    }
+
+    // [defaults]中的问题会在每个环境中重复出现,只保留一次
+    let mut seen = HashSet::new();
+    problems.retain(|problem| seen.insert((problem.line, problem.message.clone())));
+    problems.sort_by_key(|problem| problem.line.unwrap_or(0));
+    problems
+}
+
+/// 名称只包含字母、数字、_和-
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// web端部署目录在上传时会先删除,必须是根目录以外的绝对路径
+fn is_web_remote_path(path: &str) -> bool {
+    path.starts_with('/') && !path.trim_end_matches('/').is_empty()
+}
+
+/// 检查服务器地址是否为host:port格式
+fn check_server(server: &str) -> Result<(), String> {
+    let (host, port) = server
+        .rsplit_once(':')
+        .ok_or_else(|| format!("server必须是host:port格式: {}", server))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(format!("server的主机名无效: {}", server));
+    }
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err(format!("server的端口无效: {}", server)),
+    }
+}
+
+/// 在原始TOML文档中查找配置项所在的行号
+struct Locator<'a> {
+    content: &'a str,
+    /// 非TOML格式时为空,所有查找都返回None
+    doc: Option<&'a ImDocument<&'a str>>,
+}
+
+impl Locator<'_> {
+    /// 环境的继承链: 当前环境、extends的环境……,最后是[defaults]
+    fn chain(&self, env: &str) -> Vec<String> {
+        let mut chain = vec![];
+        let mut current = Some(env.to_string());
+        while let Some(name) = current.take() {
+            if chain.contains(&name) {
+                break;
+            }
+            current = self
+                .environment(&name)
+                .and_then(|table| table.get("extends"))
+                .and_then(|item| item.as_str())
+                .map(|parent| parent.to_string());
+            chain.push(name);
+        }
+        chain
+    }
+
+    fn environment(&self, name: &str) -> Option<&dyn TableLike> {
+        self.doc?
+            .as_table()
+            .get("environments")
+            .and_then(|item| item.as_table_like())
+            .and_then(|environments| environments.get(name))
+            .and_then(|item| item.as_table_like())
+    }
+
+    /// 依次在继承链和[defaults]中查找路径,返回找到的第一个位置的行号和所在的表名
+    fn find(&self, chain: &[String], path: &[&str]) -> Option<(usize, String)> {
+        let root = self.doc?.as_table() as &dyn TableLike;
+        if chain.is_empty() {
+            return self.find_in(root, path).map(|line| (line, String::new()));
+        }
+        for env in chain {
+            let environments = root.get("environments").and_then(|item| item.as_table_like())?;
+            let (key, item) = environments.get_key_value(env)?;
+            let section = format!("environments.{}", env);
+            if path.is_empty() {
+                return key.span().map(|span| (line_of(self.content, span.start), section));
+            }
+            if let Some(line) = item.as_table_like().and_then(|table| self.find_in(table, path)) {
+                return Some((line, section));
+            }
+        }
+        root.get("defaults")
+            .and_then(|item| item.as_table_like())
+            .and_then(|defaults| self.find_in(defaults, path))
+            .map(|line| (line, "defaults".to_string()))
+    }
+
+    fn find_in(&self, table: &dyn TableLike, path: &[&str]) -> Option<usize> {
+        let (first, rest) = path.split_first()?;
+        let (key, item) = table.get_key_value(first)?;
+        let line = key.span().map(|span| line_of(self.content, span.start));
+        if rest.is_empty() {
+            return line;
+        }
+        self.find_in_item(item, rest).or(line)
+    }
+
+    fn find_in_item(&self, item: &Item, path: &[&str]) -> Option<usize> {
+        if let Some(table) = item.as_table_like() {
+            return self.find_in(table, path);
+        }
+        let (first, rest) = path.split_first()?;
+        let index: usize = first.parse().ok()?;
+        if let Some(tables) = item.as_array_of_tables() {
+            let table = tables.get(index)?;
+            let line = table.span().map(|span| line_of(self.content, span.start));
+            return self.find_in(table, rest).or(line);
+        }
+        let value = item.as_array()?.get(index)?;
+        let line = value.span().map(|span| line_of(self.content, span.start));
+        match value.as_inline_table() {
+            Some(table) => self.find_in(table, rest).or(line),
+            None => line,
+        }
+    }
+}
+
+/// 字节偏移量对应的行号(从1开始)
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(
+        content: &str,
+        java_envs: &[&str],
+        vue_envs: &[&str],
+    ) -> Vec<(Option<usize>, String)> {
+        let java_envs: Vec<String> = java_envs.iter().map(|env| env.to_string()).collect();
+        let vue_envs: Vec<String> = vue_envs.iter().map(|env| env.to_string()).collect();
+        check_content(content, ConfigFormat::Toml, &java_envs, &vue_envs)
+            .into_iter()
+            .map(|problem| (problem.line, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn check_server_requires_host_and_port() {
+        assert!(check_server("example.com:22").is_ok());
+        assert!(check_server("[::1]:2222").is_ok());
+        assert!(check_server("example.com").is_err());
+        assert!(check_server(":22").is_err());
+        assert!(check_server("example.com:0").is_err());
+        assert!(check_server("example.com:ssh").is_err());
+        assert!(check_server("bad host:22").is_err());
+    }
+
+    #[test]
+    fn reports_problems_with_line_numbers() {
+        let problems = messages(
+            r#"[environments.prod]
+server = "prod"
+username = "root"
+java_path = "/usr/bin/java"
+remote_base_path = "opt/apps"
+jar_files = "admin.jar"
+prot = 22
+"#,
+            &["prod"],
+            &[],
+        );
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(problems[0].0, Some(2));
+        assert!(problems[0].1.contains("host:port"));
+        assert_eq!(problems[1].0, Some(5));
+        assert!(problems[1].1.contains("绝对路径"));
+        assert_eq!(problems[2].0, Some(7));
+        assert!(problems[2].1.contains("prot"));
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let problems = messages(
+            r#"[defaults]
+username = "root"
+java_path = "/usr/bin/java"
+remote_base_path = "/opt/apps"
+scripts = "build"
+output_dir = "dist"
+
+[environments.prod]
+server = "prod:22"
+jar_files = ["admin.jar", "client.jar"]
+"#,
+            &["prod"],
+            &["prod"],
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn web_paths_must_be_safe() {
+        assert!(is_plain_name("admin-h5_2"));
+        assert!(!is_plain_name(""));
+        assert!(!is_plain_name("."));
+        assert!(!is_plain_name(".."));
+        assert!(!is_plain_name("a/../.."));
+        assert!(!is_plain_name("a b"));
+        assert!(is_web_remote_path("/usr/share/nginx/html/admin"));
+        assert!(!is_web_remote_path("/"));
+        assert!(!is_web_remote_path("//"));
+        assert!(!is_web_remote_path("html/admin"));
+    }
+}